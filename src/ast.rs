@@ -1,25 +1,35 @@
-use crate::ast::Expr::{Assign, Binary, Call, Grouping, Literal, Logical, Unary, Variable};
-use crate::ast::Stmt::{Block, Expression, Func, If, Print, Return, Var, While};
+use crate::ast::Expr::{
+    Assign, Binary, Call, Grouping, Lambda, Literal, Logical, Unary, Variable,
+};
+use crate::ast::Stmt::{
+    Block, Export, Expression, Func, If, Import, Print, Return, Throw, Try, Var, While,
+};
 use crate::token::{Token, Value};
 
 #[derive(Debug, Clone)]
 pub enum Stmt {
     Expression(Expr),
-    Print(Expr),
+    Print(Token, Expr),
     Var(Token, Option<Expr>),
     Block(Vec<Stmt>),
     If(Expr, Box<Stmt>, Box<Option<Stmt>>),
-    While(Expr, Box<Stmt>),
+    // `🌀` or `⏳` keyword, condition, body
+    While(Token, Expr, Box<Stmt>),
     Func(Token, Vec<Token>, Vec<Stmt>),
-    Return(Token, Option<Expr>)
+    Return(Token, Option<Expr>),
+    Throw(Token, Expr),
+    // body, catch parameter, catch body, finally body
+    Try(Vec<Stmt>, Option<Token>, Option<Vec<Stmt>>, Option<Vec<Stmt>>),
+    Import(Token, Token),
+    Export(Token, Box<Stmt>),
 }
 
 impl Stmt {
     pub fn expression(expr: Expr) -> Self {
         Expression(expr)
     }
-    pub fn print(expr: Expr) -> Self {
-        Print(expr)
+    pub fn print(keyword: Token, expr: Expr) -> Self {
+        Print(keyword, expr)
     }
     pub fn block(stmts: Vec<Stmt>) -> Self {
         Block(stmts)
@@ -27,8 +37,8 @@ impl Stmt {
     pub fn if_(condition: Expr, then_branch: Stmt, else_branch: Option<Stmt>) -> Self {
         If(condition, Box::new(then_branch), Box::new(else_branch))
     }
-    pub fn while_(condition: Expr, body: Stmt) -> Self {
-        While(condition, Box::new(body))
+    pub fn while_(keyword: Token, condition: Expr, body: Stmt) -> Self {
+        While(keyword, condition, Box::new(body))
     }
     pub fn func(name: Token, params: Vec<Token>, body: Vec<Stmt>) -> Self {
         Func(name, params, body)
@@ -36,11 +46,28 @@ impl Stmt {
     pub fn return_(keyword: Token, value: Option<Expr>) -> Self {
         Return(keyword, value)
     }
+    pub fn throw(keyword: Token, value: Expr) -> Self {
+        Throw(keyword, value)
+    }
+    pub fn try_(
+        body: Vec<Stmt>,
+        catch_param: Option<Token>,
+        catch_body: Option<Vec<Stmt>>,
+        finally_body: Option<Vec<Stmt>>,
+    ) -> Self {
+        Try(body, catch_param, catch_body, finally_body)
+    }
+    pub fn import(keyword: Token, path: Token) -> Self {
+        Import(keyword, path)
+    }
+    pub fn export(keyword: Token, declaration: Stmt) -> Self {
+        Export(keyword, Box::new(declaration))
+    }
 }
 
 pub trait StmtVisitor<T> {
     fn visit_expression_stmt(&mut self, stmt: Expr) -> T;
-    fn visit_print_stmt(&mut self, stmt: Expr) -> T;
+    fn visit_print_stmt(&mut self, keyword: Token, stmt: Expr) -> T;
     fn visit_var_stmt(&mut self, name: Token, initializer: Option<Expr>) -> T;
     fn visit_block_stmt(&mut self, stmts: Vec<Stmt>) -> T;
     fn visit_if_stmt(
@@ -49,22 +76,38 @@ pub trait StmtVisitor<T> {
         then_branch: Box<Stmt>,
         else_branch: Box<Option<Stmt>>,
     ) -> T;
-    fn visit_while_stmt(&mut self, condition: Expr, body: Box<Stmt>) -> T;
+    fn visit_while_stmt(&mut self, keyword: Token, condition: Expr, body: Box<Stmt>) -> T;
     fn visit_func_stmt(&mut self, name: Token, params: Vec<Token>, body: Vec<Stmt>) -> T;
     fn visit_return_stmt(&mut self, keyword: Token, value: Option<Expr>) -> T;
+    fn visit_throw_stmt(&mut self, keyword: Token, value: Expr) -> T;
+    fn visit_try_stmt(
+        &mut self,
+        body: Vec<Stmt>,
+        catch_param: Option<Token>,
+        catch_body: Option<Vec<Stmt>>,
+        finally_body: Option<Vec<Stmt>>,
+    ) -> T;
+    fn visit_import_stmt(&mut self, keyword: Token, path: Token) -> T;
+    fn visit_export_stmt(&mut self, keyword: Token, declaration: Box<Stmt>) -> T;
     fn execute(&mut self, stmt: Stmt) -> T {
         // println!(">>> [execute] {:?}", stmt);
         match stmt {
             Expression(expr) => self.visit_expression_stmt(expr),
-            Print(expr) => self.visit_print_stmt(expr),
+            Print(keyword, expr) => self.visit_print_stmt(keyword, expr),
             Var(name, initializer) => self.visit_var_stmt(name, initializer),
             Block(stmts) => self.visit_block_stmt(stmts),
             If(condition, then_branch, else_branch) => {
                 self.visit_if_stmt(condition, then_branch, else_branch)
             }
-            While(condition, body) => self.visit_while_stmt(condition, body),
+            While(keyword, condition, body) => self.visit_while_stmt(keyword, condition, body),
             Func(name, params, body) => self.visit_func_stmt(name, params, body),
-            Return(keyword, value) => self.visit_return_stmt(keyword, value)
+            Return(keyword, value) => self.visit_return_stmt(keyword, value),
+            Throw(keyword, value) => self.visit_throw_stmt(keyword, value),
+            Try(body, catch_param, catch_body, finally_body) => {
+                self.visit_try_stmt(body, catch_param, catch_body, finally_body)
+            }
+            Import(keyword, path) => self.visit_import_stmt(keyword, path),
+            Export(keyword, declaration) => self.visit_export_stmt(keyword, declaration),
         }
     }
 }
@@ -73,12 +116,13 @@ pub trait StmtVisitor<T> {
 pub enum Expr {
     Binary(Box<Expr>, Token, Box<Expr>),
     Unary(Token, Box<Expr>),
-    Literal(Value),
+    Literal(Token, Value),
     Grouping(Box<Expr>),
     Variable(Token),
     Assign(Token, Box<Expr>),
     Logical(Box<Expr>, Token, Box<Expr>),
     Call(Box<Expr>, Token, Vec<Expr>),
+    Lambda(Token, Vec<Token>, Vec<Stmt>),
 }
 
 impl Expr {
@@ -91,8 +135,8 @@ impl Expr {
     pub fn grouping(expr: Expr) -> Self {
         Grouping(Box::new(expr))
     }
-    pub fn literal(value: Value) -> Self {
-        Literal(value)
+    pub fn literal(token: Token, value: Value) -> Self {
+        Literal(token, value)
     }
     pub fn variable(variable: Token) -> Self {
         Variable(variable)
@@ -106,17 +150,21 @@ impl Expr {
     pub fn call(callee: Expr, paren: Token, args: Vec<Expr>) -> Self {
         Call(Box::new(callee), paren, args)
     }
+    pub fn lambda(keyword: Token, params: Vec<Token>, body: Vec<Stmt>) -> Self {
+        Lambda(keyword, params, body)
+    }
 }
 
 pub trait ExprVisitor<T> {
     fn visit_binary_expr(&mut self, left: Box<Expr>, operator: Token, right: Box<Expr>) -> T;
     fn visit_unary_expr(&mut self, operator: Token, right: Box<Expr>) -> T;
-    fn visit_literal_expr(&mut self, value: Value) -> T;
+    fn visit_literal_expr(&mut self, token: Token, value: Value) -> T;
     fn visit_grouping_expr(&mut self, expr: Box<Expr>) -> T;
     fn visit_variable_expr(&mut self, variable: Token) -> T;
     fn visit_assignment_expr(&mut self, name: Token, expr: Box<Expr>) -> T;
     fn visit_logical_expr(&mut self, left: Box<Expr>, operator: Token, right: Box<Expr>) -> T;
     fn visit_call_expr(&mut self, callee: Box<Expr>, paren: Token, args: Vec<Expr>) -> T;
+    fn visit_lambda_expr(&mut self, keyword: Token, params: Vec<Token>, body: Vec<Stmt>) -> T;
     fn evaluate(&mut self, expr: Expr) -> T {
         match expr {
             Binary(left, op, right) => {
@@ -127,9 +175,9 @@ pub trait ExprVisitor<T> {
                 // println!(">>> [evaluate] Unary: {:?} {:?}", op.token_type, right);
                 self.visit_unary_expr(op, right)
             }
-            Literal(value) => {
+            Literal(token, value) => {
                 // println!(">>> [evaluate] Literal: {:?}", value);
-                self.visit_literal_expr(value)
+                self.visit_literal_expr(token, value)
             }
             Grouping(inner) => {
                 // println!(">>> [evaluate] Grouping: {:?}", inner);
@@ -151,6 +199,10 @@ pub trait ExprVisitor<T> {
                 // println!(">>> [evaluate] Call: {:?} {:?} {:?}", callee, paren.token_type, args);
                 self.visit_call_expr(callee, paren, args)
             }
+            Lambda(keyword, params, body) => {
+                // println!(">>> [evaluate] Lambda: {:?} {:?}", params, body);
+                self.visit_lambda_expr(keyword, params, body)
+            }
         }
     }
 }
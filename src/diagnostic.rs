@@ -0,0 +1,342 @@
+use crate::message::{word, word_with, Code, Message, Word};
+use crate::runtime_error::{Frame, RuntimeError};
+use crate::source::{excerpt, locate, Excerpt};
+use logos::Span;
+use serde_json::json;
+use std::fmt::{Display, Formatter};
+use std::io::IsTerminal;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    fn word(self) -> &'static str {
+        match self {
+            Severity::Error => word(Word::Error),
+            Severity::Warning => word(Word::Warning),
+            Severity::Note => word(Word::Note),
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Note => BLUE,
+        }
+    }
+}
+
+/// Another place a diagnostic points at, like where an unclosed block was opened
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpanLabel {
+    pub span: Span,
+    pub message: String,
+}
+
+/// An error or warning about the code, with everything needed to show it to a person
+/// (as a snippet of the code) or to a tool (as JSON)
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    // Unset for errors raised by `🧨`, which have no code of their own
+    pub code: Option<Code>,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<SpanLabel>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+    // For runtime errors, the calls the error happened inside of, innermost first
+    pub trace: Vec<Frame>,
+}
+
+impl Diagnostic {
+    pub fn error(span: Span, message: impl Into<Message>) -> Self {
+        let message = message.into();
+        Self {
+            code: Some(message.code),
+            severity: Severity::Error,
+            message: message.to_string(),
+            span,
+            labels: Vec::new(),
+            notes: message.notes,
+            help: message.help,
+            trace: Vec::new(),
+        }
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Display) -> Self {
+        self.labels.push(SpanLabel {
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Display) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    /// Renders the diagnostic in the format picked with `set_error_format`
+    pub fn format(&self) -> String {
+        match error_format() {
+            ErrorFormat::Human => self.render(use_color()),
+            ErrorFormat::Json => self.to_json().to_string(),
+        }
+    }
+
+    /// Prints the diagnostic to stderr
+    pub fn emit(&self) {
+        eprintln!("{}", self.format());
+    }
+
+    /// Renders the diagnostic with a snippet of the code under it:
+    ///
+    /// ```text
+    /// error[E025]: Expected '🫷' after block
+    ///  --> test.emoji:3:1
+    ///   |
+    /// 1 | 🤔🫱x🫲 🫸
+    ///   |         -- opened here
+    /// 3 |
+    ///   | ^
+    /// ```
+    pub fn render(&self, color: bool) -> String {
+        let paint = |style: &str, text: &str| {
+            if color {
+                format!("{}{}{}", style, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+        let code = self.code.map_or(String::new(), |code| format!("[{}]", code));
+        let header = format!("{}{}", self.severity.word(), code);
+        let mut out = format!(
+            "{}{}",
+            paint(&format!("{}{}", BOLD, self.severity.color()), &header),
+            paint(BOLD, &format!(": {}", self.message))
+        );
+
+        let primary = excerpt(&self.span);
+        let labels: Vec<(Excerpt, &SpanLabel)> = self
+            .labels
+            .iter()
+            .filter_map(|label| Some((excerpt(&label.span)?, label)))
+            .collect();
+        let gutter = primary
+            .iter()
+            .chain(labels.iter().map(|(excerpt, _)| excerpt))
+            .map(|excerpt| excerpt.location.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let bar = paint(BLUE, &format!("{} |", " ".repeat(gutter)));
+
+        let location = primary.as_ref().map_or_else(|| locate(self.span.start), |e| e.location.clone());
+        out += &format!("\n{}{} {}", " ".repeat(gutter), paint(BLUE, "-->"), location);
+        out += &format!("\n{}", bar);
+        // Labels earlier in the same file come first, like reading the code top to bottom
+        let (before, after): (Vec<_>, Vec<_>) = labels.iter().partition(|(excerpt, _)| {
+            excerpt.location.file == location.file && excerpt.location.line < location.line
+        });
+        for (excerpt, label) in before {
+            out += &snippet(excerpt, gutter, &paint(BLUE, &marker(excerpt, '-', &label.message)), &paint);
+        }
+        // Labels on the primary line go under it, without repeating the line
+        let (inline, after): (Vec<_>, Vec<_>) = after.into_iter().partition(|(excerpt, _)| {
+            excerpt.location.file == location.file && excerpt.location.line == location.line
+        });
+        if let Some(excerpt) = &primary {
+            let style = format!("{}{}", BOLD, self.severity.color());
+            out += &snippet(excerpt, gutter, &paint(&style, &marker(excerpt, '^', "")), &paint);
+            for (label_excerpt, label) in inline {
+                let marker = marker(label_excerpt, '-', &label.message);
+                out += &format!("\n{} {}", bar, paint(BLUE, &marker));
+            }
+        }
+        for (excerpt, label) in after {
+            if excerpt.location.file != location.file {
+                out += &format!("\n{}{} {}", " ".repeat(gutter), paint(BLUE, ":::"), excerpt.location);
+            }
+            out += &snippet(excerpt, gutter, &paint(BLUE, &marker(excerpt, '-', &label.message)), &paint);
+        }
+        if !self.trace.is_empty() {
+            let prefix = format!("{} = {}:", " ".repeat(gutter), word(Word::StackTrace));
+            out += &format!("\n{}", paint(BOLD, &prefix));
+            for line in trace_lines(&self.trace) {
+                out += &format!("\n{}     {}", " ".repeat(gutter), line);
+            }
+        }
+        for note in &self.notes {
+            let prefix = format!("{} = {}:", " ".repeat(gutter), word(Word::Note));
+            out += &format!("\n{} {}", paint(BOLD, &prefix), note);
+        }
+        if let Some(help) = &self.help {
+            let prefix = format!("{} = {}:", " ".repeat(gutter), word(Word::Help));
+            out += &format!("\n{} {}", paint(BOLD, &prefix), help);
+        }
+        out
+    }
+
+    /// The diagnostic as one JSON object, for editors and other tools
+    pub fn to_json(&self) -> serde_json::Value {
+        let position = |span: &Span| {
+            let start = locate(span.start);
+            let end = locate(span.end);
+            json!({
+                "file": start.file,
+                "line": start.line,
+                "column": start.column,
+                "end_line": end.line,
+                "end_column": end.column,
+            })
+        };
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        };
+        json!({
+            "code": self.code.map(Code::as_str),
+            "severity": severity,
+            "message": self.message,
+            "span": position(&self.span),
+            "labels": self.labels.iter().map(|label| json!({
+                "message": label.message,
+                "span": position(&label.span),
+            })).collect::<Vec<_>>(),
+            "notes": self.notes,
+            "help": self.help,
+            "trace": self.trace.iter().map(|frame| json!({
+                "function": frame.function.lexeme,
+                "call_site": position(&frame.call_site),
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(error: &RuntimeError) -> Self {
+        Self {
+            code: error.code,
+            severity: Severity::Error,
+            message: error.message.clone(),
+            span: error.span.clone(),
+            labels: Vec::new(),
+            notes: error.notes.clone(),
+            help: error.help.clone(),
+            trace: error.trace.clone(),
+        }
+    }
+}
+
+/// One-line form, `[file:line:column] error[E010]: message`
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let code = self.code.map_or(String::new(), |code| format!("[{}]", code));
+        let location = locate(self.span.start);
+        write!(f, "[{}] {}{}: {}", location, self.severity.word(), code, self.message)
+    }
+}
+
+// One line per frame, with runs of the same call (like a function recursing from the same
+// place) shown once, followed by how many more times it repeats
+fn trace_lines(trace: &[Frame]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut frames = trace.iter().peekable();
+    while let Some(frame) = frames.next() {
+        let location = locate(frame.call_site.start).to_string();
+        lines.push(word_with(Word::CalledAt, &[frame.function.lexeme.clone(), location]));
+        let same_call = |next: &&Frame| {
+            next.function.lexeme == frame.function.lexeme && next.call_site == frame.call_site
+        };
+        let mut repeats = 0;
+        while frames.next_if(same_call).is_some() {
+            repeats += 1;
+        }
+        if repeats > 0 {
+            lines.push(word_with(Word::Repeated, &[repeats.to_string()]));
+        }
+    }
+    lines
+}
+
+// A source line with a marker line under it
+fn snippet(excerpt: &Excerpt, gutter: usize, marker: &str, paint: &impl Fn(&str, &str) -> String) -> String {
+    let number = format!("{:>width$} |", excerpt.location.line, width = gutter);
+    let bar = format!("{} |", " ".repeat(gutter));
+    format!("\n{} {}\n{} {}", paint(BLUE, &number), excerpt.line, paint(BLUE, &bar), marker)
+}
+
+// `^^^` or `--- message` under the excerpt's span, lined up with how wide each character
+// shows in a terminal, so markers sit under emoji too
+fn marker(excerpt: &Excerpt, symbol: char, message: &str) -> String {
+    let chars: Vec<char> = excerpt.line.chars().collect();
+    let start = (excerpt.location.column - 1).min(chars.len());
+    let end = (excerpt.end_column - 1).clamp(start, chars.len());
+    let indent: String = chars[..start]
+        .iter()
+        .map(|&c| if c == '\t' { "\t".to_string() } else { " ".repeat(display_width(c)) })
+        .collect();
+    let width = chars[start..end].iter().map(|&c| display_width(c)).sum::<usize>().max(1);
+    let marker = format!("{}{}", indent, symbol.to_string().repeat(width));
+    if message.is_empty() {
+        marker
+    } else {
+        format!("{} {}", marker, message)
+    }
+}
+
+// Close enough for code: emoji take two columns, joiners and variation selectors none
+fn display_width(c: char) -> usize {
+    match c as u32 {
+        0x200D | 0xFE00..=0xFE0F | 0x0300..=0x036F => 0,
+        0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2300..=0x23FF | 0x2B00..=0x2BFF => 2,
+        _ => 1,
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const BLUE: &str = "\x1b[34m";
+
+fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none() && std::io::stderr().is_terminal()
+}
+
+/// How diagnostics are written: for people, or as one JSON object per line for tools
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!("Unknown error format '{}', expected human or json", s)),
+        }
+    }
+}
+
+static ERROR_FORMAT: Mutex<ErrorFormat> = Mutex::new(ErrorFormat::Human);
+
+pub fn set_error_format(format: ErrorFormat) {
+    *ERROR_FORMAT.lock().unwrap_or_else(|e| e.into_inner()) = format;
+}
+
+pub fn error_format() -> ErrorFormat {
+    *ERROR_FORMAT.lock().unwrap_or_else(|e| e.into_inner())
+}
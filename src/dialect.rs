@@ -0,0 +1,208 @@
+use crate::suggest::closest;
+use crate::token::TokenType;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+const BUILTIN: &str = include_str!("../dialects/default.toml");
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spellings {
+    #[serde(default)]
+    emoji: Vec<String>,
+    #[serde(default)]
+    text: Vec<String>,
+    #[serde(default)]
+    shortcode: Vec<String>,
+    #[serde(default)]
+    similar: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(transparent)]
+struct DialectFile {
+    tokens: HashMap<TokenType, PartialSpellings>,
+}
+
+// What a custom dialect file may override for one token
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PartialSpellings {
+    emoji: Option<Vec<String>>,
+    text: Option<Vec<String>>,
+    shortcode: Option<Vec<String>>,
+    similar: Option<Vec<String>>,
+}
+
+/// How keywords, operators and delimiters are spelled, both in emoji-lang code and in the
+/// text code the translator reads. The built-in dialect lives in `dialects/default.toml`.
+#[derive(Clone, Debug)]
+pub struct Dialect {
+    spellings: HashMap<TokenType, Spellings>,
+    // Every emoji and text spelling, longest first so matching always takes the longest one
+    emoji: Vec<(String, TokenType)>,
+    text: Vec<(String, TokenType)>,
+    shortcode: Vec<(String, TokenType)>,
+    similar: Vec<(String, TokenType)>,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl Dialect {
+    pub fn builtin() -> Self {
+        let mut spellings = HashMap::new();
+        Self::apply(&mut spellings, BUILTIN)
+            .and_then(|()| Self::from_spellings(spellings))
+            .expect("the built-in dialect is valid")
+    }
+
+    /// Loads a dialect file on top of the built-in dialect
+    pub fn load(path: &str) -> Result<Self> {
+        Self::builtin().with_file(path)
+    }
+
+    /// Applies a dialect file on top of this dialect
+    pub fn with_file(self, path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read dialect file {}", path))?;
+        self.with_overrides(&contents)
+            .with_context(|| format!("Invalid dialect file {}", path))
+    }
+
+    /// Applies dialect TOML on top of this dialect; tokens it doesn't mention keep their spellings
+    pub fn with_overrides(self, contents: &str) -> Result<Self> {
+        let mut spellings = self.spellings;
+        Self::apply(&mut spellings, contents)?;
+        Self::from_spellings(spellings)
+    }
+
+    fn apply(spellings: &mut HashMap<TokenType, Spellings>, contents: &str) -> Result<()> {
+        let file: DialectFile = toml::from_str(contents)?;
+        for (token_type, overrides) in file.tokens {
+            // Text and comments keep their emoji delimiters, but may still have shortcodes
+            let shortcode_only = matches!(token_type, TokenType::Text | TokenType::Comment)
+                && overrides.emoji.is_none()
+                && overrides.text.is_none()
+                && overrides.similar.is_none();
+            if !token_type.has_spelling() && !shortcode_only {
+                bail!("{:?} is read by the lexer itself and can't be respelled", token_type);
+            }
+            let entry = spellings.entry(token_type).or_default();
+            if let Some(emoji) = overrides.emoji {
+                entry.emoji = emoji;
+            }
+            if let Some(text) = overrides.text {
+                entry.text = text;
+            }
+            if let Some(shortcode) = overrides.shortcode {
+                if let Some(bad) = shortcode.iter().find(|s| !is_shortcode(s)) {
+                    bail!("Shortcode '{}' for {:?} must look like ':name:'", bad, token_type);
+                }
+                entry.shortcode = shortcode;
+            }
+            if let Some(similar) = overrides.similar {
+                entry.similar = similar;
+            }
+        }
+        Ok(())
+    }
+
+    fn from_spellings(spellings: HashMap<TokenType, Spellings>) -> Result<Self> {
+        let index = |field: fn(&Spellings) -> &Vec<String>| {
+            let mut index: Vec<(String, TokenType)> = spellings
+                .iter()
+                .flat_map(|(token_type, s)| field(s).iter().map(|word| (word.clone(), *token_type)))
+                .filter(|(word, _)| !word.is_empty())
+                .collect();
+            index.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+            index
+        };
+        let emoji = index(|s| &s.emoji);
+        let text = index(|s| &s.text);
+        let shortcode = index(|s| &s.shortcode);
+        let similar = index(|s| &s.similar);
+        let pairs = emoji.windows(2).chain(text.windows(2)).chain(shortcode.windows(2));
+        for pair in pairs.chain(similar.windows(2)) {
+            let ((a, a_type), (b, b_type)) = (&pair[0], &pair[1]);
+            if a == b && a_type != b_type {
+                bail!("'{}' spells both {:?} and {:?}", a, a_type, b_type);
+            }
+        }
+        Ok(Self {
+            spellings,
+            emoji,
+            text,
+            shortcode,
+            similar,
+        })
+    }
+
+    /// The spelling the translator writes for a token
+    pub fn emoji_for(&self, token_type: TokenType) -> Option<&str> {
+        self.spellings.get(&token_type)?.emoji.first().map(String::as_str)
+    }
+
+    /// The spelling `emoji-translator --reverse` writes for a token
+    pub fn text_for(&self, token_type: TokenType) -> Option<&str> {
+        self.spellings.get(&token_type)?.text.first().map(String::as_str)
+    }
+
+    /// Every `:shortcode:` spelling of a token
+    pub fn shortcodes_for(&self, token_type: TokenType) -> &[String] {
+        self.spellings.get(&token_type).map_or(&[], |s| &s.shortcode)
+    }
+
+    /// Finds the longest emoji spelling at the start of `source`, and its length in bytes
+    pub fn match_emoji(&self, source: &str) -> Option<(TokenType, usize)> {
+        longest_match(&self.emoji, source)
+    }
+
+    /// Finds the longest text spelling at the start of `source`, and its length in bytes
+    pub fn match_text(&self, source: &str) -> Option<(TokenType, usize)> {
+        longest_match(&self.text, source)
+    }
+
+    /// Finds the longest `:shortcode:` spelling at the start of `source`, so `:clap::clap:`
+    /// reads as `👏👏` rather than two `👏`s
+    pub fn match_shortcode(&self, source: &str) -> Option<(TokenType, usize)> {
+        longest_match(&self.shortcode, source)
+    }
+
+    /// Finds an emoji at the start of `source` that looks or means like a token's spelling
+    /// without being one, like `🙌` for `👏`, and its length in bytes
+    pub fn match_similar(&self, source: &str) -> Option<(TokenType, usize)> {
+        longest_match(&self.similar, source)
+    }
+
+    /// The token whose shortcode is spelled most like `shortcode`, for a misspelled `:clapp:`
+    pub fn closest_shortcode(&self, shortcode: &str) -> Option<TokenType> {
+        let found = closest(shortcode, self.shortcode.iter().map(|(spelling, _)| spelling.as_str()))?;
+        self.match_shortcode(found).map(|(token_type, _)| token_type)
+    }
+}
+
+fn is_shortcode(spelling: &str) -> bool {
+    spelling.len() > 2 && spelling.starts_with(':') && spelling.ends_with(':')
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn longest_match(index: &[(String, TokenType)], source: &str) -> Option<(TokenType, usize)> {
+    index.iter().find_map(|(spelling, token_type)| {
+        let rest = source.strip_prefix(spelling.as_str())?;
+        // A spelling made of letters, like `while`, must not match the start of `whileLoop`
+        let ends_in_word = spelling.chars().next_back().is_some_and(is_word_char);
+        if ends_in_word && rest.chars().next().is_some_and(is_word_char) {
+            return None;
+        }
+        Some((*token_type, spelling.len()))
+    })
+}
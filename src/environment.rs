@@ -1,6 +1,7 @@
+use crate::message::{did_you_mean, Code, Message};
+use crate::suggest::closest;
 use crate::token::Token;
 use crate::token::Value;
-use anyhow::{Result, anyhow};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -29,23 +30,49 @@ impl Environment {
     pub fn define(&mut self, name: String, value: Value) {
         self.values.borrow_mut().insert(name, value);
     }
-    pub fn get(&self, name: &Token) -> Result<Value> {
-        if let Some(v) = self.values.borrow().get(&name.lexeme) {
-            Ok(v.clone())
+    // Looks a name up in this scope only, without walking the enclosing ones
+    pub fn get_local(&self, name: &str) -> Option<Value> {
+        self.values.borrow().get(name).cloned()
+    }
+    pub fn get(&self, name: &Token) -> Result<Value, Message> {
+        self.lookup(&name.lexeme).ok_or_else(|| self.undefined(&name.lexeme))
+    }
+    pub fn assign(&mut self, name: Token, value: Value) -> Result<(), Message> {
+        match self.assign_existing(&name.lexeme, value) {
+            true => Ok(()),
+            false => Err(self.undefined(&name.lexeme)),
+        }
+    }
+    fn lookup(&self, name: &str) -> Option<Value> {
+        match self.values.borrow().get(name) {
+            Some(v) => Some(v.clone()),
+            None => self.enclosing.as_ref()?.borrow().lookup(name),
+        }
+    }
+    fn assign_existing(&mut self, name: &str, value: Value) -> bool {
+        if self.values.borrow().contains_key(name) {
+            self.define(name.to_string(), value);
+            true
         } else if let Some(env) = &self.enclosing {
-            env.borrow().get(name)
+            env.borrow_mut().assign_existing(name, value)
         } else {
-            Err(anyhow!("Undefined variable '{}'", name.lexeme))
+            false
         }
     }
-    pub fn assign(&mut self, name: Token, value: Value) -> Result<()> {
-        if self.values.borrow().contains_key(&name.lexeme) {
-            self.define(name.lexeme, value);
-            Ok(())
-        } else if let Some(env) = &mut self.enclosing {
-            env.borrow_mut().assign(name, value)
-        } else {
-            Err(anyhow!("Undefined variable '{}'", name.lexeme))
+    fn undefined(&self, name: &str) -> Message {
+        Message::new(Code::UndefinedVariable).arg(name).with_help(self.suggest(name))
+    }
+    // A "did you mean" hint naming the binding in scope spelled most like `name`
+    fn suggest(&self, name: &str) -> Option<String> {
+        let names = self.names();
+        closest(name, names.iter().map(String::as_str)).map(|found| did_you_mean(format!("'{}'", found)))
+    }
+    // Every name visible from this scope, including the enclosing ones
+    fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.values.borrow().keys().cloned().collect();
+        if let Some(env) = &self.enclosing {
+            names.extend(env.borrow().names());
         }
+        names
     }
 }
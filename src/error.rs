@@ -1,37 +1,60 @@
-use crate::token::{Token, TokenType};
+use crate::diagnostic::Diagnostic;
+use crate::message::Message;
+use crate::token::Token;
 use anyhow::{Result, anyhow};
 use logos::Span;
+use std::cell::RefCell;
 use std::sync::atomic::{AtomicBool, Ordering};
 
 static HAD_ERROR: AtomicBool = AtomicBool::new(false);
 
-pub fn error_at<T>(token: Token, message: &str) -> Result<T> {
-    error(token.span, message);
-    Err(anyhow!(message.to_string()))
+thread_local! {
+    // Set while `capture_errors` runs, so errors are collected instead of printed
+    static CAPTURED: RefCell<Option<Vec<Diagnostic>>> = const { RefCell::new(None) };
 }
 
-pub fn error(span: Span, message: &str) {
-    report(span, "", message);
+/// Runs `f`, returning the errors it reported instead of printing them
+pub fn capture_errors<T>(f: impl FnOnce() -> T) -> (T, Vec<Diagnostic>) {
+    let outer = CAPTURED.with(|captured| captured.borrow_mut().replace(Vec::new()));
+    let result = f();
+    let reported = CAPTURED.with(|captured| captured.replace(outer));
+    (result, reported.unwrap_or_default())
 }
 
-pub fn error_at_token(token: &Token, message: &str) {
-    if token.token_type == TokenType::EndOfFile {
-        report(token.span.clone(), " at end", message);
-    } else {
-        report(
-            token.span.clone(),
-            &format!(" at '{}'", token.lexeme),
-            message,
-        );
-    }
+pub fn error_at<T>(token: Token, message: impl Into<Message>) -> Result<T> {
+    report_err(Diagnostic::error(token.span, message))
+}
+
+pub fn error(span: Span, message: impl Into<Message>) {
+    report(Diagnostic::error(span, message));
+}
+
+pub fn error_at_token(token: &Token, message: impl Into<Message>) {
+    report(Diagnostic::error(token.span.clone(), message));
+}
+
+/// Reports a diagnostic and returns it as an `Err`, for parsing code that bails out
+pub fn report_err<T>(diagnostic: Diagnostic) -> Result<T> {
+    let message = diagnostic.message.clone();
+    report(diagnostic);
+    Err(anyhow!(message))
 }
 
-fn report(span: Span, where_: &str, message: &str) {
-    println!("[line {:?}] Error{}: {}", span, where_, message);
+pub fn report(diagnostic: Diagnostic) {
     set_had_error(true);
+    let diagnostic = CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+        Some(captured) => {
+            captured.push(diagnostic);
+            None
+        }
+        None => Some(diagnostic),
+    });
+    if let Some(diagnostic) = diagnostic {
+        diagnostic.emit();
+    }
 }
 
-fn had_error() -> bool {
+pub fn had_error() -> bool {
     HAD_ERROR.load(Ordering::Relaxed)
 }
 
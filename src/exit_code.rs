@@ -0,0 +1,11 @@
+//! Process exit codes, following the BSD `sysexits.h` conventions so scripts can tell
+//! what kind of failure happened.
+
+/// The command line was wrong (`EX_USAGE`)
+pub const USAGE: i32 = 64;
+/// A file to read the code (or its source map) from couldn't be read (`EX_NOINPUT`)
+pub const NO_INPUT: i32 = 66;
+/// The code has lexing or parsing errors, so it never ran (`EX_DATAERR`)
+pub const COMPILE: i32 = 65;
+/// The code ran, but stopped on an uncaught runtime error (`EX_SOFTWARE`)
+pub const RUNTIME: i32 = 70;
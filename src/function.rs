@@ -2,14 +2,16 @@ use crate::ast::Stmt;
 use crate::environment::{EnvPtr, Environment};
 use crate::interpreter::Interpreter;
 use crate::return_value::Return;
+use crate::runtime_error::Frame;
 use crate::token::{Token, Value};
 use std::fmt::{Display, Formatter};
+use std::rc::Rc;
 use std::panic;
 use std::panic::{catch_unwind, AssertUnwindSafe};
 
 pub trait Callable {
     fn arity(&self) -> usize;
-    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Value;
+    fn call(&self, interpreter: &mut Interpreter, paren: &Token, args: Vec<Value>) -> Value;
 }
 
 #[derive(Debug, Clone)]
@@ -39,34 +41,47 @@ impl Callable for EmojiFunction {
         self.params.len()
     }
 
-    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Value {
-        let env = Environment::new_enclosed(self.closure.clone());
-        for (param, arg) in self.params.iter().zip(args.into_iter()) {
-            env.borrow_mut().define(param.lexeme.clone(), arg);
-        }
-        // println!(">>> calling function: {}", self.name.lexeme);
-        let result = catch_unwind(AssertUnwindSafe(|| {
-            interpreter.execute_block(self.body.clone(), env);
-            // println!(">>> block executed successfully");
-            Value::Nil
-        }));
-        // println!(">>> caught panic? {:?}", result.is_err());
-        match result {
-            Ok(val) => val,
-            Err(payload) => {
-                if let Some(ret) = payload.downcast_ref::<Return>() {
-                    // println!(">>> caught Return!");
-                    return ret.value.clone();
+    fn call(&self, interpreter: &mut Interpreter, paren: &Token, args: Vec<Value>) -> Value {
+        interpreter.push_frame(Frame::new(self.name.clone(), paren.span.clone()));
+        // `🤞` blocks around the call don't keep the calls inside it from being tail calls
+        let outer_tries = interpreter.swap_tries(0);
+        // A tail call replaces this one and runs in this same loop, so recursion written as
+        // `🔙 f🫱...🫲` takes constant Rust stack however deep it goes
+        let mut tail_function: Option<Rc<EmojiFunction>> = None;
+        let mut args = args;
+        loop {
+            let function = tail_function.as_deref().unwrap_or(self);
+            let env = Environment::new_enclosed(function.closure.clone());
+            for (param, arg) in function.params.iter().zip(args) {
+                env.borrow_mut().define(param.lexeme.clone(), arg);
+            }
+            // println!(">>> calling function: {}", function.name.lexeme);
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                interpreter.execute_block(function.body.clone(), env);
+                // println!(">>> block executed successfully");
+                Value::Nil
+            }));
+            // println!(">>> caught panic? {:?}", result.is_err());
+            let ret = match result {
+                Ok(val) => Return::new(val),
+                Err(payload) => match payload.downcast::<Return>() {
+                    Ok(ret) => *ret,
+                    // The frame stays on the stack while an error unwinds, so whoever catches
+                    // it can still see where it happened
+                    Err(payload) => panic::resume_unwind(payload),
+                },
+            };
+            match ret.tail_call {
+                Some(call) => {
+                    interpreter.replace_frame(Frame::new(call.function.name.clone(), call.paren.span));
+                    args = call.args;
+                    tail_function = Some(call.function);
                 }
-                if let Some(msg) = payload.downcast_ref::<&str>() {
-                    println!(">>> Panic msg: {}", msg);
-                } else if let Some(msg) = payload.downcast_ref::<String>() {
-                    println!(">>> Panic msg: {}", msg);
-                } else {
-                    println!(">>> Unknown panic type.");
+                None => {
+                    interpreter.pop_frame();
+                    interpreter.swap_tries(outer_tries);
+                    return ret.value;
                 }
-
-                panic::resume_unwind(payload);
             }
         }
     }
@@ -1,14 +1,29 @@
 use crate::ast::{Expr, ExprVisitor, Stmt, StmtVisitor};
 use crate::environment::{EnvPtr, Environment};
 use crate::function::{Callable, EmojiFunction};
+use crate::error::{capture_errors, report};
+use crate::lexer::{scan_tokens, LexerOptions};
+use crate::limits::{InterpreterConfig, Usage};
+use crate::message::{word, Code, Message, Word};
+use crate::module::{Exports, Modules};
+use crate::native;
+use crate::parser::Parser;
+use crate::permissions::Permissions;
 use crate::return_value::Return;
-use crate::token::Value::{Boolean, Function, Nil, Number, Text};
+use crate::runtime_error::{throw, Frame, RuntimeError};
+use crate::token::Value::{Boolean, Error, Function, NativeFunction, Nil, Number, Text};
+use crate::source;
+use crate::source::locate;
 use crate::token::{Token, TokenType, Value};
+use logos::Span;
 use panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
-use std::mem::replace;
+use std::fs;
+use std::mem::{replace, take};
+use std::path::Path;
 use std::panic;
 use std::panic::panic_any;
 use std::rc::Rc;
+use std::thread;
 use TokenType::{
     Bang, BangEqual, EqualEqual, Greater, GreaterEqual, Less, LessEqual, Minus, Or, Plus, Slash,
     Star, TextConcat,
@@ -16,24 +31,178 @@ use TokenType::{
 
 pub struct Interpreter {
     environment: EnvPtr,
+    lexer_options: LexerOptions,
+    modules: Modules,
+    // Names marked with `📤` by the module that is currently running
+    exports: Vec<String>,
+    // The emoji function calls currently running, outermost first
+    call_stack: Vec<Frame>,
+    config: InterpreterConfig,
+    usage: Usage,
+    // How many `🤞` blocks of the running function the code is inside. A `🔙` in one makes
+    // its call right away rather than as a tail call, so the `🥅` and `🏁` see how it ends.
+    tries: usize,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        Self::with_config(InterpreterConfig::default())
+    }
+    pub fn with_config(config: InterpreterConfig) -> Self {
+        let usage = Usage::start(&config);
+        Self {
+            environment: Self::globals(&config),
+            lexer_options: LexerOptions::default(),
+            modules: Modules::default(),
+            exports: Vec::new(),
+            call_stack: Vec::new(),
+            config,
+            usage,
+            tries: 0,
+        }
+    }
+    // Every module gets its own top-level scope, starting out with just the built-ins
+    fn globals(config: &InterpreterConfig) -> EnvPtr {
         let environment = Environment::new();
-        Self { environment }
+        let file_system = if config.file_system { native::file_system() } else { Vec::new() };
+        for function in native::globals().into_iter().chain(file_system) {
+            let name = function.name.to_string();
+            environment
+                .borrow_mut()
+                .define(name, NativeFunction(Rc::new(function)));
+        }
+        environment
+    }
+    pub fn set_lexer_options(&mut self, options: LexerOptions) {
+        self.lexer_options = options;
+    }
+    /// Marks the file being run as already loading, so importing it back is reported as a cycle
+    pub fn set_entry_file(&mut self, path: &str) {
+        if let Ok(path) = fs::canonicalize(path) {
+            let _ = self.modules.start(path);
+        }
+    }
+    /// What the code may touch outside the interpreter, for built-ins to check
+    pub fn permissions(&self) -> &Permissions {
+        &self.config.permissions
+    }
+    /// Counts a text of `len` bytes a built-in is about to return against the limits
+    pub fn allocate_text(&mut self, len: usize) -> Result<(), Message> {
+        self.usage.allocate_text(&self.config, len)
+    }
+    pub fn define_global(&mut self, name: &str, value: Value) {
+        self.environment.borrow_mut().define(name.to_string(), value);
+    }
+    pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<(), RuntimeError> {
+        self.usage = Usage::start(&self.config);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            for stmt in stmts {
+                self.execute(stmt)
+            }
+        }));
+        match result {
+            Ok(()) => Ok(()),
+            Err(payload) => match payload.downcast::<RuntimeError>() {
+                Ok(error) => Err(self.unwound(*error, 0)),
+                Err(payload) => resume_unwind(payload),
+            },
+        }
+    }
+    /// Enters a call, or raises a catchable "stack overflow" error at its call site if that
+    /// would nest more than `max_depth` calls or come close to running out of stack
+    pub fn push_frame(&mut self, frame: Frame) {
+        let depth = self.call_stack.len();
+        let result = if depth >= self.config.max_depth {
+            Err(Message::new(Code::StackOverflow).arg(self.config.max_depth))
+        } else {
+            self.usage.check_stack(&self.config, depth)
+        };
+        if let Err(message) = result {
+            throw(RuntimeError::new(frame.call_site, message.with_note(word(Word::TailCalls))));
+        }
+        self.step(&frame.call_site);
+        self.call_stack.push(frame);
+    }
+    pub fn pop_frame(&mut self) {
+        self.call_stack.pop();
     }
-    pub fn interpret(&mut self, stmts: Vec<Stmt>) {
-        for stmt in stmts {
-            self.execute(stmt)
+    /// Swaps the running call's frame for the tail call it hands over to
+    pub fn replace_frame(&mut self, frame: Frame) {
+        self.step(&frame.call_site);
+        if let Some(top) = self.call_stack.last_mut() {
+            *top = frame;
         }
     }
+    /// Sets how many `🤞` blocks the code is inside, returning the count it replaces
+    pub fn swap_tries(&mut self, tries: usize) -> usize {
+        replace(&mut self.tries, tries)
+    }
+    // Counts a step against the configured limits, raising their error at `span` once one is passed
+    fn step(&mut self, span: &Span) {
+        let result = self.usage.step(&self.config);
+        result.unwrap_or_else(|e| throw(RuntimeError::new(span.clone(), e)));
+    }
+    // Counts a text the code builds, from a literal or `🥂`, against the limits, raising
+    // their error at `span` once one is passed
+    fn new_text(&mut self, text: String, span: &Span) -> Value {
+        let result = self.usage.allocate_text(&self.config, text.len());
+        result.unwrap_or_else(|e| throw(RuntimeError::new(span.clone(), e)));
+        Text(text)
+    }
+    // Runs `f`, recording the trace of any error that escapes it (see `unwound`)
+    fn catching(&mut self, depth: usize, f: impl FnOnce(&mut Self)) -> thread::Result<()> {
+        catch_unwind(AssertUnwindSafe(|| f(self))).map_err(|payload| {
+            match payload.downcast::<RuntimeError>() {
+                Ok(error) => Box::new(self.unwound(*error, depth)),
+                Err(payload) => payload,
+            }
+        })
+    }
+    // Records the calls an error unwound through into its trace, the first time it is caught,
+    // and drops them from the call stack down to `depth`, where it was caught
+    fn unwound(&mut self, mut error: RuntimeError, depth: usize) -> RuntimeError {
+        if error.trace.is_empty() {
+            error.trace = self.call_stack.iter().rev().cloned().collect();
+        }
+        self.call_stack.truncate(depth);
+        error
+    }
     const fn is_truthy(value: &Value) -> bool {
         // Boolean(true) is the only truly “truthy” Boolean;
         // Nil and Boolean(false) are falsy;
         // Everything else is considered truthy by implication.
         matches!(value, Boolean(true)) || !matches!(value, Nil | Boolean(false))
     }
+    // Evaluates a call's callee and arguments, and checks the callee can be called with them
+    fn evaluate_call(&mut self, callee: Expr, paren: &Token, args: Vec<Expr>) -> (Value, Vec<Value>) {
+        let callee = self.evaluate(callee);
+        let mut arguments = Vec::with_capacity(args.len());
+        for arg in args {
+            arguments.push(self.evaluate(arg));
+        }
+        let arity = match &callee {
+            Function(f) => f.arity(),
+            NativeFunction(f) => f.arity(),
+            _ => throw(RuntimeError::new(paren.span.clone(), Code::NotCallable)),
+        };
+        if arguments.len() != arity {
+            throw(RuntimeError::new(
+                paren.span.clone(),
+                Message::new(Code::ArityMismatch)
+                    .arg(arity)
+                    .arg(arguments.len()),
+            ));
+        }
+        (callee, arguments)
+    }
+    // The callee of a call `evaluate_call` already checked
+    fn callable(callee: &Value) -> &dyn Callable {
+        match callee {
+            Function(f) => f.as_ref(),
+            NativeFunction(f) => f.as_ref(),
+            _ => unreachable!("evaluate_call only returns callable values"),
+        }
+    }
     fn is_equal(a: Value, b: Value) -> bool {
         match (a, b) {
             (Nil, Nil) => true,
@@ -47,6 +216,43 @@ impl Interpreter {
             (_, _) => false,
         }
     }
+    fn load_module(&mut self, path: &Path, import: &Token) -> Exports {
+        let fail = |message: Message| -> ! { throw(RuntimeError::new(import.span.clone(), message)) };
+        let name = path.display().to_string();
+        let contents = fs::read_to_string(path)
+            .unwrap_or_else(|e| fail(Message::new(Code::ModuleUnreadable).arg(&name).arg(e)));
+
+        let base = source::add_file(&name, &contents);
+        // Collected rather than checked through `had_error`, which stays set once any
+        // earlier error was reported, even one a `🥅` caught
+        let ((stmts, data), errors) = capture_errors(|| {
+            let scanned = scan_tokens(&contents, base, &self.lexer_options);
+            (Parser::new(scanned.tokens).parse(), scanned.data)
+        });
+        if !errors.is_empty() {
+            errors.into_iter().for_each(report);
+            fail(Message::new(Code::ModuleSyntaxErrors).arg(&name));
+        }
+
+        let env = Self::globals(&self.config);
+        let data = data.map_or(Nil, Text);
+        env.borrow_mut().define("DATA".to_string(), data);
+        let outer_exports = take(&mut self.exports);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            self.execute_block(stmts, env.clone());
+        }));
+        let names = replace(&mut self.exports, outer_exports);
+        if let Err(payload) = result {
+            resume_unwind(payload);
+        }
+
+        let env = env.borrow();
+        let exports = names
+            .into_iter()
+            .filter_map(|name| env.get_local(&name).map(|value| (name, value)))
+            .collect();
+        Rc::new(exports)
+    }
     pub fn execute_block(&mut self, stmts: Vec<Stmt>, new_env: EnvPtr) {
         let previous = replace(&mut self.environment, new_env);
         let result = catch_unwind(AssertUnwindSafe(|| {
@@ -61,13 +267,22 @@ impl Interpreter {
     }
 }
 
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StmtVisitor<()> for Interpreter {
     fn visit_expression_stmt(&mut self, stmt: Expr) {
         self.evaluate(stmt);
     }
-    fn visit_print_stmt(&mut self, stmt: Expr) {
+    fn visit_print_stmt(&mut self, keyword: Token, stmt: Expr) {
         let value = self.evaluate(stmt);
-        println!("{}", value);
+        let line = format!("{}\n", value);
+        let result = self.usage.write_output(&self.config, line.len());
+        result.unwrap_or_else(|e| throw(RuntimeError::new(keyword.span, e)));
+        print!("{}", line);
     }
     fn visit_var_stmt(&mut self, name: Token, initializer: Option<Expr>) {
         let value = initializer.map_or_else(|| Nil, |initializer| self.evaluate(initializer));
@@ -88,8 +303,9 @@ impl StmtVisitor<()> for Interpreter {
             self.execute(else_branch)
         }
     }
-    fn visit_while_stmt(&mut self, condition: Expr, body: Box<Stmt>) {
+    fn visit_while_stmt(&mut self, keyword: Token, condition: Expr, body: Box<Stmt>) {
         while Self::is_truthy(&self.evaluate(condition.clone())) {
+            self.step(&keyword.span);
             self.execute(*body.clone())
         }
     }
@@ -107,10 +323,98 @@ impl StmtVisitor<()> for Interpreter {
     }
     fn visit_return_stmt(&mut self, _keyword: Token, value: Option<Expr>) {
         // println!(">>> returning {:?}", value);
-        let return_value = value.map(|expr| self.evaluate(expr)).unwrap_or(Nil);
+        let in_function = !self.call_stack.is_empty();
+        let return_value = match value {
+            // Rather than calling an emoji function here, hand the call to the caller to make
+            Some(Expr::Call(callee, paren, args)) if in_function && self.tries == 0 => {
+                let (callee, arguments) = self.evaluate_call(*callee, &paren, args);
+                match callee {
+                    Function(function) => panic_any(Return::tail_call(function, paren, arguments)),
+                    callee => Self::callable(&callee).call(self, &paren, arguments),
+                }
+            }
+            value => value.map(|expr| self.evaluate(expr)).unwrap_or(Nil),
+        };
         // println!(">>> throwing return: {}", return_value);
         panic_any(Return::new(return_value));
     }
+    fn visit_throw_stmt(&mut self, keyword: Token, value: Expr) {
+        match self.evaluate(value) {
+            // Rethrowing a caught error keeps where it originally happened
+            Error(error) => throw((*error).clone()),
+            value => throw(RuntimeError::thrown(keyword.span, value.to_string())),
+        }
+    }
+    fn visit_import_stmt(&mut self, keyword: Token, path: Token) {
+        let fail = |message: Message| -> ! { throw(RuntimeError::new(path.span.clone(), message)) };
+        let importer = locate(keyword.span.start).file;
+        let resolved = Modules::resolve(&importer, &path.lexeme).unwrap_or_else(|e| fail(e));
+        let exports = match self.modules.cached(&resolved) {
+            Some(exports) => exports,
+            None => {
+                self.modules.start(resolved.clone()).unwrap_or_else(|e| fail(e));
+                let result = catch_unwind(AssertUnwindSafe(|| self.load_module(&resolved, &path)));
+                match result {
+                    Ok(exports) => {
+                        self.modules.finish(exports.clone());
+                        exports
+                    }
+                    Err(payload) => {
+                        self.modules.abort();
+                        resume_unwind(payload);
+                    }
+                }
+            }
+        };
+        for (name, value) in exports.iter() {
+            self.environment
+                .borrow_mut()
+                .define(name.clone(), value.clone());
+        }
+    }
+    fn visit_export_stmt(&mut self, _keyword: Token, declaration: Box<Stmt>) {
+        let name = match &*declaration {
+            Stmt::Var(name, _) | Stmt::Func(name, _, _) => name.lexeme.clone(),
+            _ => unreachable!("the parser only allows exporting declarations"),
+        };
+        self.execute(*declaration);
+        self.exports.push(name);
+    }
+    fn visit_try_stmt(
+        &mut self,
+        body: Vec<Stmt>,
+        catch_param: Option<Token>,
+        catch_body: Option<Vec<Stmt>>,
+        finally_body: Option<Vec<Stmt>>,
+    ) {
+        let depth = self.call_stack.len();
+        let outer_tries = self.swap_tries(self.tries + 1);
+        let mut result = self.catching(depth, |this| this.visit_block_stmt(body));
+        if let Some(catch_body) = catch_body {
+            // Only emoji-level errors are caught; returns and interpreter bugs keep unwinding
+            if let Err(payload) = result {
+                result = match payload.downcast::<RuntimeError>() {
+                    Ok(error) if error.code.is_some_and(Code::is_limit) => Err(error),
+                    Ok(error) => self.catching(depth, |this| {
+                        let env = Environment::new_enclosed(this.environment.clone());
+                        if let Some(param) = catch_param {
+                            env.borrow_mut().define(param.lexeme, Error(Rc::new(*error)));
+                        }
+                        this.execute_block(catch_body, env);
+                    }),
+                    Err(payload) => Err(payload),
+                };
+            }
+        }
+        self.tries = outer_tries;
+        // A `🔙` or `🧨` inside `🏁` replaces whatever was unwinding before it
+        if let Some(finally_body) = finally_body {
+            self.visit_block_stmt(finally_body);
+        }
+        if let Err(payload) = result {
+            resume_unwind(payload);
+        }
+    }
 }
 
 impl ExprVisitor<Value> for Interpreter {
@@ -120,15 +424,25 @@ impl ExprVisitor<Value> for Interpreter {
         // println!(">>> [binary] {:?} {:?} {:?}", left, operator.token_type, right);
         match operator.token_type {
             TextConcat => match (left, right) {
-                (Text(l), Text(r)) => Text(l + &r),
-                (l, r) => panic!("Type mismatch for concatenation: {:?} 🪡 {:?}", l, r),
+                (Text(l), Text(r)) => self.new_text(l + &r, &operator.span),
+                (l, r) => throw(RuntimeError::new(
+                    operator.span,
+                    Message::new(Code::ConcatenationTypeMismatch)
+                        .arg(format!("{:?}", l))
+                        .arg(format!("{:?}", r)),
+                )),
             },
             EqualEqual => Boolean(Self::is_equal(left, right)),
             BangEqual => Boolean(!Self::is_equal(left, right)),
             Minus | Star | Slash | Plus | Greater | GreaterEqual | Less | LessEqual => {
                 let (l, r) = match (left, right) {
                     (Number(l), Number(r)) => (l, r),
-                    (l, r) => panic!("Operands must be numbers: {:?} and {:?}", l, r),
+                    (l, r) => throw(RuntimeError::new(
+                        operator.span,
+                        Message::new(Code::OperandsMustBeNumbers)
+                            .arg(format!("{:?}", l))
+                            .arg(format!("{:?}", r)),
+                    )),
                 };
                 match operator.token_type {
                     Minus => Number(l - r),
@@ -150,7 +464,10 @@ impl ExprVisitor<Value> for Interpreter {
         match operator.token_type {
             Minus => match right {
                 Number(number) => Number(-number),
-                _ => panic!("{:?} is not a number", right),
+                _ => throw(RuntimeError::new(
+                    operator.span,
+                    Message::new(Code::NotANumber).arg(format!("{:?}", right)),
+                )),
             },
             Bang => match right {
                 Boolean(bool) => Boolean(bool),
@@ -160,23 +477,26 @@ impl ExprVisitor<Value> for Interpreter {
             _ => Nil,
         }
     }
-    fn visit_literal_expr(&mut self, value: Value) -> Value {
-        value.into()
+    fn visit_literal_expr(&mut self, token: Token, value: Value) -> Value {
+        match value {
+            Text(text) => self.new_text(text, &token.span),
+            value => value,
+        }
     }
     fn visit_grouping_expr(&mut self, expr: Box<Expr>) -> Value {
         self.evaluate(*expr)
     }
     fn visit_variable_expr(&mut self, variable: Token) -> Value {
         // println!(">>> [variable lookup] {}", variable.lexeme);
-        self.environment.borrow().get(&variable).unwrap()
+        let value = self.environment.borrow().get(&variable);
+        value.unwrap_or_else(|e| throw(RuntimeError::new(variable.span, e)))
     }
     fn visit_assignment_expr(&mut self, name: Token, value: Box<Expr>) -> Value {
         let value = self.evaluate(*value);
         // println!(">>> [assign] {} = {:?}", name.lexeme.clone(), value);
-        self.environment
-            .borrow_mut()
-            .assign(name, value.clone())
-            .unwrap();
+        let span = name.span.clone();
+        let result = self.environment.borrow_mut().assign(name, value.clone());
+        result.unwrap_or_else(|e| throw(RuntimeError::new(span, e)));
         value
     }
     fn visit_logical_expr(&mut self, left: Box<Expr>, operator: Token, right: Box<Expr>) -> Value {
@@ -189,25 +509,15 @@ impl ExprVisitor<Value> for Interpreter {
             self.evaluate(*right)
         }
     }
-    fn visit_call_expr(&mut self, callee: Box<Expr>, _paren: Token, args: Vec<Expr>) -> Value {
+    fn visit_call_expr(&mut self, callee: Box<Expr>, paren: Token, args: Vec<Expr>) -> Value {
         // println!(">>> calling {:?} with {:?}", callee, args);
-        let callee = self.evaluate(*callee);
-        let mut arguments = Vec::with_capacity(args.len());
-        for arg in args {
-            arguments.push(self.evaluate(arg));
-        }
-        match callee {
-            Function(f) => {
-                if arguments.len() != f.arity() {
-                    panic!(
-                        "Expected {} arguments but got {} instead.",
-                        f.arity(),
-                        arguments.len()
-                    );
-                }
-                f.call(self, arguments)
-            }
-            _ => panic!("Only functions are callable."),
-        }
+        let (callee, arguments) = self.evaluate_call(*callee, &paren, args);
+        Self::callable(&callee).call(self, &paren, arguments)
+    }
+    fn visit_lambda_expr(&mut self, keyword: Token, params: Vec<Token>, body: Vec<Stmt>) -> Value {
+        // Anonymous functions close over the current scope just like named ones
+        let name = Token::new(keyword.token_type, "anonymous".to_string(), None, keyword.span);
+        let function = EmojiFunction::new_from(name, params, body, self.environment.clone());
+        Function(Rc::new(function))
     }
 }
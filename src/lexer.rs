@@ -0,0 +1,273 @@
+use crate::dialect::Dialect;
+use crate::error::error;
+use crate::message::{did_you_mean, Code, Message};
+use crate::token::{Token, TokenType, Value};
+use logos::{Logos, Span};
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub struct LexerOptions {
+    // When set, a line break ends a statement wherever a `✊` could have
+    pub newline_terminators: bool,
+    // When set, `:shortcode:` spellings from the dialect are accepted alongside emoji
+    pub shortcodes: bool,
+    pub dialect: Arc<Dialect>,
+}
+
+impl Default for LexerOptions {
+    fn default() -> Self {
+        Self {
+            newline_terminators: true,
+            shortcodes: false,
+            dialect: Arc::new(Dialect::builtin()),
+        }
+    }
+}
+
+/// Every token in a source, comments and line breaks included, with spans relative to it.
+/// Keywords and operators come from the dialect; logos reads everything else.
+pub struct RawTokens<'a> {
+    source: &'a str,
+    dialect: &'a Dialect,
+    shortcodes: bool,
+    position: usize,
+}
+
+impl<'a> RawTokens<'a> {
+    pub fn new(source: &'a str, dialect: &'a Dialect) -> Self {
+        Self {
+            source,
+            dialect,
+            shortcodes: false,
+            position: 0,
+        }
+    }
+
+    /// Also reads the dialect's `:shortcode:` spellings
+    pub fn with_shortcodes(mut self, shortcodes: bool) -> Self {
+        self.shortcodes = shortcodes;
+        self
+    }
+
+    /// The length of a token spelled with a shortcode at the start of `rest`. A `:thread:`
+    /// text runs to the next `:thread:`, and a `:speaking_head:` comment to the end of the line.
+    fn shortcode_len(&self, rest: &str) -> Option<(Result<TokenType, ()>, usize)> {
+        let (token_type, len) = self.dialect.match_shortcode(rest)?;
+        let len = match token_type {
+            TokenType::Text => {
+                let line_end = rest.find(['\n', '\r']).unwrap_or(rest.len());
+                let closing = (len..line_end).filter(|&i| rest.is_char_boundary(i)).find_map(|i| {
+                    match self.dialect.match_shortcode(&rest[i..]) {
+                        Some((TokenType::Text, close)) => Some(i + close),
+                        _ => None,
+                    }
+                });
+                match closing {
+                    Some(end) => end,
+                    // An unterminated text is an error, like an unterminated `🧵`
+                    None => return Some((Err(()), len)),
+                }
+            }
+            TokenType::Comment => rest.find(['\n', '\r']).unwrap_or(rest.len()),
+            _ => len,
+        };
+        Some((Ok(token_type), len))
+    }
+}
+
+impl Iterator for RawTokens<'_> {
+    type Item = (Result<TokenType, ()>, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.source[self.position..];
+        let start = self.position + (rest.len() - rest.trim_start_matches([' ', '\t', '\r', '\x0c']).len());
+        let rest = &self.source[start..];
+        if rest.is_empty() {
+            self.position = start;
+            return None;
+        }
+        if let Some((token_type, len)) = self.dialect.match_emoji(rest) {
+            self.position = start + len;
+            return Some((Ok(token_type), start..self.position));
+        }
+        if self.shortcodes
+            && let Some((result, len)) = self.shortcode_len(rest)
+        {
+            self.position = start + len;
+            return Some((result, start..self.position));
+        }
+        // A look-alike of a keyword, or an unknown `:shortcode:`, is one error, not one per
+        // code point of a multi-part emoji or one per colon
+        let unknown = self.dialect.match_similar(rest).map(|(_, len)| len);
+        if let Some(len) = unknown.or_else(|| shortcode_like(rest).map(str::len)) {
+            self.position = start + len;
+            return Some((Err(()), start..self.position));
+        }
+        let mut lexer = TokenType::lexer(rest);
+        let result = lexer.next()?;
+        // Always make progress, even if logos gives back an empty match
+        let end = lexer.span().end.max(rest.chars().next().map_or(1, char::len_utf8));
+        self.position = start + end;
+        Some((result, start..self.position))
+    }
+}
+
+// Suggests the keyword meant by an unknown emoji, or by a `:shortcode:` that is misspelled
+// or used without `--shortcodes`
+fn suggest(rest: &str, dialect: &Dialect) -> Option<String> {
+    let token_type = match dialect.match_similar(rest) {
+        Some((token_type, _)) => token_type,
+        None => {
+            let shortcode = shortcode_like(rest)?;
+            match dialect.match_shortcode(shortcode) {
+                Some((token_type, len)) if len == shortcode.len() => token_type,
+                _ => dialect.closest_shortcode(shortcode)?,
+            }
+        }
+    };
+    let emoji = dialect.emoji_for(token_type)?;
+    let spelling = match dialect.shortcodes_for(token_type).first() {
+        Some(shortcode) => format!("'{}' ({})", emoji, shortcode),
+        None => format!("'{}'", emoji),
+    };
+    Some(did_you_mean(spelling))
+}
+
+// The `:name:` at the start of `rest`, whether or not the dialect knows it
+fn shortcode_like(rest: &str) -> Option<&str> {
+    let name = rest.strip_prefix(':')?;
+    let name_len = name.find(|c: char| !(c.is_ascii_alphanumeric() || "_+-".contains(c)))?;
+    rest.get(..name_len + 2).filter(|s| name_len > 0 && s.ends_with(':'))
+}
+
+pub struct Scanned {
+    pub tokens: Vec<Token>,
+    // Everything after an explicit `🔚`, exposed to programs as `DATA`
+    pub data: Option<String>,
+}
+
+/// Scans `source`, offsetting every span by `base` (see `source::add_file`)
+pub fn scan_tokens(source: &str, base: usize, options: &LexerOptions) -> Scanned {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut data = None;
+    let raw_tokens = RawTokens::new(source, &options.dialect).with_shortcodes(options.shortcodes);
+    for (result, local_span) in raw_tokens {
+        let span = local_span.start + base..local_span.end + base;
+        let raw_slice = &source[local_span.clone()];
+        let Ok(token_type) = result else {
+            let help = suggest(&source[local_span.start..], &options.dialect);
+            error(span, Message::new(Code::UnexpectedCharacter).arg(raw_slice).with_help(help));
+            continue;
+        };
+        match token_type {
+            TokenType::Comment => continue,
+            TokenType::Newline => {
+                if options.newline_terminators {
+                    push_newline(&mut tokens, span);
+                }
+                continue;
+            }
+            // Like Go, a closing `🫷` or the end of the file also ends the line before it
+            TokenType::RightCurlyBrace | TokenType::EndOfFile if options.newline_terminators => {
+                push_newline(&mut tokens, span.start..span.start);
+            }
+            _ => {}
+        }
+        let mut slice = raw_slice.to_string();
+        if token_type == TokenType::Text {
+            slice = text_contents(raw_slice, &options.dialect).to_string();
+        }
+        let value = match token_type {
+            TokenType::Text => Some(Value::Text(slice.clone())),
+            TokenType::Number => slice.parse().ok().map(Value::Number),
+            TokenType::True => Some(Value::Boolean(true)),
+            TokenType::False => Some(Value::Boolean(false)),
+            _ => None,
+        };
+        let is_end = token_type == TokenType::EndOfFile;
+        let token = Token::new(token_type, slice, value, span.clone());
+        // println!(">>> [lexer] token: {:?}", token);
+        tokens.push(token);
+
+        // `🔚` is an optional explicit terminator; whatever follows it is data, not code
+        if is_end {
+            let rest = &source[local_span.end..];
+            let rest = rest
+                .strip_prefix("\r\n")
+                .or_else(|| rest.strip_prefix('\n'))
+                .unwrap_or(rest);
+            data = Some(rest.to_string());
+            break;
+        }
+    }
+
+    // Files without a `🔚` still get one, so the parser always has something to stop at
+    if data.is_none() {
+        let end = base + source.len();
+        if options.newline_terminators {
+            push_newline(&mut tokens, end..end);
+        }
+        tokens.push(Token::new(TokenType::EndOfFile, String::new(), None, end..end));
+    }
+    Scanned { tokens, data }
+}
+
+/// The contents of a text token, without its `🧵` or `:thread:` delimiters
+pub fn text_contents<'s>(slice: &'s str, dialect: &Dialect) -> &'s str {
+    if let Some(inner) = slice.strip_prefix('🧵').and_then(|s| s.strip_suffix('🧵')) {
+        return inner;
+    }
+    let delimiters = dialect.shortcodes_for(TokenType::Text);
+    let open = delimiters.iter().find(|d| slice.starts_with(d.as_str()));
+    let close = delimiters.iter().find(|d| slice.ends_with(d.as_str()));
+    match (open, close) {
+        (Some(open), Some(close)) if open.len() + close.len() <= slice.len() => {
+            &slice[open.len()..slice.len() - close.len()]
+        }
+        _ => slice,
+    }
+}
+
+/// Rewrites `:shortcode:` spellings in `source` into their emoji, leaving everything else,
+/// including text contents, comments and the data after `🔚`, exactly as it was
+pub fn expand_shortcodes(source: &str, dialect: &Dialect) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut copied = 0;
+    for (token, span) in RawTokens::new(source, dialect).with_shortcodes(true) {
+        let slice = &source[span.clone()];
+        result.push_str(&source[copied..span.start]);
+        copied = span.end;
+        match token {
+            _ if !slice.starts_with(':') => result.push_str(slice),
+            Ok(TokenType::Text) => {
+                result.push('🧵');
+                result.push_str(text_contents(slice, dialect));
+                result.push('🧵');
+            }
+            Ok(TokenType::Comment) => {
+                let comment = dialect
+                    .match_shortcode(slice)
+                    .map_or(slice, |(_, len)| &slice[len..]);
+                result.push('🗣');
+                result.push_str(comment);
+            }
+            Ok(token_type) => result.push_str(dialect.emoji_for(token_type).unwrap_or(slice)),
+            Err(()) => result.push_str(slice),
+        }
+        if token == Ok(TokenType::EndOfFile) {
+            break;
+        }
+    }
+    result.push_str(&source[copied..]);
+    result
+}
+
+// Newlines only matter right after a token that can end a statement, so drop the rest
+fn push_newline(tokens: &mut Vec<Token>, span: Span) {
+    if tokens
+        .last()
+        .is_some_and(|token| token.token_type.can_end_statement())
+    {
+        tokens.push(Token::new(TokenType::Newline, String::new(), None, span));
+    }
+}
@@ -0,0 +1,21 @@
+pub mod ast;
+pub mod diagnostic;
+pub mod dialect;
+pub mod environment;
+pub mod error;
+pub mod exit_code;
+pub mod function;
+pub mod interpreter;
+pub mod lexer;
+pub mod limits;
+pub mod message;
+pub mod module;
+pub mod native;
+pub mod parser;
+pub mod permissions;
+pub mod return_value;
+pub mod runtime_error;
+pub mod source;
+pub mod source_map;
+pub mod suggest;
+pub mod token;
@@ -0,0 +1,140 @@
+use crate::message::{word, Code, Message, Word};
+use crate::permissions::Permissions;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// How many emoji function calls may run inside each other before a call raises a
+/// "stack overflow" error, unless `InterpreterConfig::max_depth` says otherwise
+pub const DEFAULT_MAX_DEPTH: usize = 10_000;
+
+/// Limits on what code run by an `Interpreter` may use, for running code that can't be
+/// trusted. They apply to each `Interpreter::interpret` call; `None` means no limit, which
+/// is the default for everything but `max_depth`. `permissions` says what the code may
+/// touch outside the interpreter, and denies everything by default.
+/// `file_system` defines the built-ins that work with files, which are left out by default.
+#[derive(Clone, Debug)]
+pub struct InterpreterConfig {
+    /// Most emoji function calls that may run inside each other
+    pub max_depth: usize,
+    /// Size in bytes of the stack `Interpreter::interpret` runs on, if known. Calls then raise
+    /// a "stack overflow" error before they run out of it, however deep `max_depth` allows.
+    pub stack_size: Option<usize>,
+    /// Most steps the code may take, counting each loop iteration and each function call
+    pub max_steps: Option<u64>,
+    /// Longest the code may run for
+    pub timeout: Option<Duration>,
+    /// Longest text the code may build, in bytes, counting text literals
+    pub max_string_len: Option<usize>,
+    /// Most bytes of text the code may build over the whole run, counting each text literal
+    /// every time it is evaluated
+    pub max_allocation: Option<usize>,
+    /// Most bytes `🖨` may write
+    pub max_output: Option<usize>,
+    pub permissions: Permissions,
+    pub file_system: bool,
+}
+
+impl Default for InterpreterConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            stack_size: None,
+            max_steps: None,
+            timeout: None,
+            max_string_len: None,
+            max_allocation: None,
+            max_output: None,
+            permissions: Permissions::default(),
+            file_system: false,
+        }
+    }
+}
+
+/// What the running code has used so far, checked against an `InterpreterConfig`.
+/// Each check returns the error to raise once a limit is passed.
+#[derive(Debug)]
+pub struct Usage {
+    // Where the stack was when the code started running
+    stack_base: usize,
+    steps: u64,
+    deadline: Option<Instant>,
+    allocated: usize,
+    output: usize,
+}
+
+impl Usage {
+    pub fn start(config: &InterpreterConfig) -> Self {
+        Self {
+            stack_base: stack_address(),
+            steps: 0,
+            deadline: config.timeout.map(|timeout| Instant::now() + timeout),
+            allocated: 0,
+            output: 0,
+        }
+    }
+
+    /// Checks that a call `depth` calls deep leaves a quarter of the stack to spare, for the
+    /// Rust frames a single call can take and for a `🥅` to handle the error in
+    pub fn check_stack(&self, config: &InterpreterConfig, depth: usize) -> Result<(), Message> {
+        match config.stack_size {
+            Some(size) if stack_address().abs_diff(self.stack_base) > size - size / 4 => {
+                let help = word(Word::StackSize).to_string();
+                Err(Message::new(Code::StackOverflow).arg(depth).with_help(Some(help)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts a loop iteration or function call, and checks the time while at it
+    pub fn step(&mut self, config: &InterpreterConfig) -> Result<(), Message> {
+        self.steps += 1;
+        if let Some(max) = config.max_steps
+            && self.steps > max
+        {
+            return Err(Message::new(Code::StepLimit).arg(max));
+        }
+        self.check_time(config)
+    }
+
+    /// Counts a new text of `len` bytes, and checks the time while at it, since building
+    /// long texts can take a while without any steps
+    pub fn allocate_text(&mut self, config: &InterpreterConfig, len: usize) -> Result<(), Message> {
+        self.check_time(config)?;
+        if let Some(max) = config.max_string_len
+            && len > max
+        {
+            return Err(Message::new(Code::StringTooLong).arg(len).arg(max));
+        }
+        self.allocated = self.allocated.saturating_add(len);
+        match config.max_allocation {
+            Some(max) if self.allocated > max => Err(Message::new(Code::AllocationLimit).arg(max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts `len` bytes about to be written by `🖨`
+    pub fn write_output(&mut self, config: &InterpreterConfig, len: usize) -> Result<(), Message> {
+        self.output = self.output.saturating_add(len);
+        match config.max_output {
+            Some(max) if self.output > max => Err(Message::new(Code::OutputLimit).arg(max)),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_time(&self, config: &InterpreterConfig) -> Result<(), Message> {
+        match self.deadline {
+            Some(deadline) if Instant::now() > deadline => {
+                let timeout = config.timeout.unwrap_or_default();
+                Err(Message::new(Code::Timeout).arg(format!("{:?}", timeout)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// Roughly where the top of the stack is right now
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    black_box(&marker) as *const u8 as usize
+}
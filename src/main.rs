@@ -1,78 +1,240 @@
-mod ast;
-mod environment;
-mod error;
-mod function;
-mod interpreter;
-mod parser;
-mod return_value;
-mod token;
-
-use crate::interpreter::Interpreter;
-use crate::parser::Parser;
-use crate::return_value::Return;
-use crate::token::{Token, TokenType};
-use clap::Parser as ArgParser;
-use logos::Logos;
-use std::panic::set_hook;
+use clap::{Parser as ArgParser, Subcommand};
+use emoji_lang::diagnostic::{set_error_format, Diagnostic, ErrorFormat};
+use emoji_lang::dialect::Dialect;
+use emoji_lang::error::had_error;
+use emoji_lang::exit_code;
+use emoji_lang::interpreter::Interpreter;
+use emoji_lang::lexer::{expand_shortcodes, scan_tokens, LexerOptions};
+use emoji_lang::limits::{InterpreterConfig, DEFAULT_MAX_DEPTH};
+use emoji_lang::message::{init_locale, Code, Locale};
+use emoji_lang::parser::Parser;
+use emoji_lang::permissions::{Allow, Permissions};
+use emoji_lang::return_value::Return;
+use emoji_lang::runtime_error::RuntimeError;
+use emoji_lang::source;
+use emoji_lang::source_map::SourceMap;
+use emoji_lang::token::Value;
+use std::fmt::Display;
 use std::fs;
-use token::Value;
+use std::panic::{resume_unwind, set_hook};
+use std::process::exit;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 #[derive(ArgParser, Debug)]
-#[command(author, version, about)]
+#[command(author, version, about, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(short, long)]
     debug: bool,
-    #[arg(short, long)]
-    file: String,
+    #[arg(short, long, required_unless_present = "explain")]
+    file: Option<String>,
+    /// Require every statement to end with `✊` instead of also ending at line breaks
+    #[arg(long)]
+    explicit_terminators: bool,
+    /// Source map written by `emoji-translator --source-map`, to report errors
+    /// against the text file the code was translated from
+    #[arg(long)]
+    source_map: Option<String>,
+    /// Also accept `:shortcode:` spellings, like `:clap:` for `👏`
+    #[arg(long)]
+    shortcodes: bool,
+    /// TOML file respelling keywords and operators (see `dialects/default.toml`)
+    #[arg(long, global = true)]
+    dialect: Option<String>,
+    /// Language of error messages: en, es, fr, pt, or emoji for glyphs only.
+    /// Defaults to the `EMOJI_LANG_LOCALE` environment variable, then en
+    #[arg(long, global = true)]
+    locale: Option<Locale>,
+    /// How errors are printed: human, with a snippet of the code, or json, one object per line
+    #[arg(long, global = true, default_value = "human")]
+    error_format: ErrorFormat,
+    /// Print a longer explanation of an error code, like E025, and exit
+    #[arg(long, value_name = "CODE")]
+    explain: Option<String>,
+    /// Most function calls that may run inside each other before a "stack overflow" error
+    #[arg(long, default_value_t = DEFAULT_MAX_DEPTH)]
+    max_depth: usize,
+    /// Stack size in MiB of the thread the code runs on; raise it along with --max-depth
+    #[arg(long, value_name = "MIB", default_value_t = 512)]
+    stack_size: usize,
+    /// Stop the code after this many steps, counting each loop iteration and function call
+    #[arg(long, value_name = "STEPS")]
+    max_steps: Option<u64>,
+    /// Stop the code after it runs for this many seconds, like 2 or 0.5
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    timeout: Option<Duration>,
+    /// Longest text the code may build, in bytes
+    #[arg(long, value_name = "BYTES")]
+    max_string_len: Option<usize>,
+    /// Most bytes of text the code may build over its whole run
+    #[arg(long, value_name = "BYTES")]
+    max_alloc: Option<usize>,
+    /// Most bytes the code may print
+    #[arg(long, value_name = "BYTES")]
+    max_output: Option<usize>,
+    /// Define the built-ins that work with files: readFile, writeFile, appendFile, listDir,
+    /// fileExists and deleteFile. They still need --allow-read or --allow-write
+    #[arg(long)]
+    fs: bool,
+    /// Let the code read files; bare allows everything, or list paths like --allow-read=data,notes.txt
+    #[arg(long, value_name = "PATHS", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    allow_read: Option<Vec<String>>,
+    /// Let the code write files; bare allows everything, or list paths like --allow-write=out
+    #[arg(long, value_name = "PATHS", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    allow_write: Option<Vec<String>>,
+    /// Let the code read environment variables; bare allows all, or list names like --allow-env=HOME
+    #[arg(long, value_name = "NAMES", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    allow_env: Option<Vec<String>>,
+    /// Let the code run programs; bare allows all, or list them like --allow-run=git,ls
+    #[arg(long, value_name = "PROGRAMS", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    allow_run: Option<Vec<String>>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Rewrite a file written with `:shortcode:` spellings into emoji
+    Expand {
+        file: String,
+        /// Write the expanded code here instead of to stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
 }
 
 fn main() {
     // Ensure Rust's default panic message is only logged for real errors and not the panics
-    // we use to exit early from a function when the interpreter encounters a return statement
+    // we use to exit early from a function when the interpreter encounters a return statement,
+    // or to unwind to the nearest `🥅` when emoji code raises an error
     set_hook(Box::new(|info| {
-        if let Some(_) = info.payload().downcast_ref::<Return>() {
+        if info.payload().is::<Return>() || info.payload().is::<RuntimeError>() {
             return;
         }
         eprintln!("❌ Panic occurred: {}", info);
     }));
 
-    let args = Args::parse();
-    let file_path = args.file;
-    let contents = fs::read_to_string(&file_path)
-        .unwrap_or_else(|_| panic!("❌ Failed to read code from file: {}", file_path));
+    let args = Args::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        // `--help` and `--version` also come through here, and aren't failures
+        exit(if e.use_stderr() { exit_code::USAGE } else { 0 })
+    });
+    init_locale(args.locale);
+    set_error_format(args.error_format);
+    if let Some(code) = &args.explain {
+        explain(code);
+        return;
+    }
+    let dialect = match &args.dialect {
+        Some(path) => Dialect::load(path).unwrap_or_else(|e| fail(exit_code::USAGE, format!("{:#}", e))),
+        None => Dialect::builtin(),
+    };
+    if let Some(Command::Expand { file, output }) = &args.command {
+        expand(file, output.as_deref(), &dialect);
+        return;
+    }
+
+    // Each nested emoji call takes several Rust frames, so deep recursion needs a far bigger
+    // stack than the main thread's
+    let runner = thread::Builder::new()
+        .name("interpreter".to_string())
+        .stack_size(args.stack_size.saturating_mul(1024 * 1024))
+        .spawn(move || run(args, dialect))
+        .unwrap_or_else(|e| fail(exit_code::USAGE, format!("Failed to start the interpreter: {}", e)));
+    if let Err(payload) = runner.join() {
+        resume_unwind(payload);
+    }
+}
+
+fn run(args: Args, dialect: Dialect) {
+    let file_path = args.file.expect("clap requires --file without a subcommand or --explain");
+    let contents = fs::read_to_string(&file_path).unwrap_or_else(|e| {
+        fail(exit_code::NO_INPUT, format!("Failed to read code from file {}: {}", file_path, e))
+    });
     if args.debug {
         println!("ℹ️ File {:?} contents are:\n{:?}", file_path, contents);
     }
 
-    let mut tokens: Vec<Token> = Vec::new();
-    let mut lexer = TokenType::lexer(contents.as_str());
-    while let Some(result) = lexer.next() {
-        let span = lexer.span();
-        let token_type = result.unwrap();
-        let raw_slice = lexer.slice().to_string();
-        let mut slice = raw_slice.clone();
-        if token_type == TokenType::Text {
-            slice = String::from(&slice[4..slice.len() - 4]);
+    let options = LexerOptions {
+        newline_terminators: !args.explicit_terminators,
+        shortcodes: args.shortcodes,
+        dialect: Arc::new(dialect),
+    };
+    let base = match &args.source_map {
+        Some(map_path) => {
+            let map = fs::read_to_string(map_path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| SourceMap::parse(&text))
+                .unwrap_or_else(|e| {
+                    fail(exit_code::NO_INPUT, format!("Failed to read source map {}: {}", map_path, e))
+                });
+            source::add_mapped_file(&file_path, &contents, map)
         }
-        let value = match token_type {
-            TokenType::Text => Some(Value::Text(slice.clone())),
-            TokenType::Number => Some(Value::Number(slice.parse().unwrap())),
-            TokenType::True => Some(Value::Boolean(true)),
-            TokenType::False => Some(Value::Boolean(false)),
-            _ => None,
-        };
-        let token = Token::new(token_type, slice, value, span);
-        // println!(">>> [lexer] token: {:?}", token);
-        tokens.push(token);
-    }
-
-    // let errors: Vec<_> = lexer.filter_map(|result| result.err()).collect();
-    // println!(">>> Lexer has errors? {}", !errors.is_empty());
+        None => source::add_file(&file_path, &contents),
+    };
+    let scanned = scan_tokens(contents.as_str(), base, &options);
 
-    let mut parser = Parser::new(tokens);
+    let mut parser = Parser::new(scanned.tokens);
     let statements = parser.parse();
     // println!(">>> [parsed] statements {:?}", statements);
+    // Never run a program that only partly parsed; its errors have already been reported
+    if had_error() {
+        exit(exit_code::COMPILE);
+    }
+
+    let mut interpreter = Interpreter::with_config(InterpreterConfig {
+        max_depth: args.max_depth,
+        stack_size: Some(args.stack_size.saturating_mul(1024 * 1024)),
+        max_steps: args.max_steps,
+        timeout: args.timeout,
+        max_string_len: args.max_string_len,
+        max_allocation: args.max_alloc,
+        max_output: args.max_output,
+        permissions: Permissions {
+            read: Allow::from_flag(args.allow_read),
+            write: Allow::from_flag(args.allow_write),
+            env: Allow::from_flag(args.allow_env),
+            run: Allow::from_flag(args.allow_run),
+        },
+        file_system: args.fs,
+    });
+    interpreter.set_lexer_options(options);
+    interpreter.set_entry_file(&file_path);
+    let data = scanned.data.map_or(Value::Nil, Value::Text);
+    interpreter.define_global("DATA", data);
+    if let Err(error) = interpreter.interpret(statements) {
+        Diagnostic::from(&error).emit();
+        exit(exit_code::RUNTIME);
+    }
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let seconds: f64 = value.parse().map_err(|_| format!("'{}' isn't a number of seconds", value))?;
+    Duration::try_from_secs_f64(seconds).map_err(|e| e.to_string())
+}
 
-    let mut interpreter = Interpreter::new();
-    interpreter.interpret(statements)
+fn fail(code: i32, message: impl Display) -> ! {
+    eprintln!("❌ {}", message);
+    exit(code)
+}
+
+fn explain(code: &str) {
+    let code: Code = code.parse().unwrap_or_else(|e| fail(exit_code::USAGE, e));
+    println!("{}: {}", code, code.explanation().trim_end());
+}
+
+fn expand(file_path: &str, output: Option<&str>, dialect: &Dialect) {
+    let contents = fs::read_to_string(file_path).unwrap_or_else(|e| {
+        fail(exit_code::NO_INPUT, format!("Failed to read code from file {}: {}", file_path, e))
+    });
+    let expanded = expand_shortcodes(&contents, dialect);
+    match output {
+        Some(output_path) => {
+            fs::write(output_path, expanded)
+                .unwrap_or_else(|e| fail(exit_code::USAGE, format!("Failed to write to file {}: {}", output_path, e)));
+            println!("✅ Wrote expanded code to {}", output_path);
+        }
+        None => print!("{}", expanded),
+    }
 }
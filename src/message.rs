@@ -0,0 +1,379 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::{LazyLock, Mutex};
+
+/// Every error emoji-lang reports itself. The code is what users see (`E001`), and what
+/// the message catalogs in `messages/` are keyed by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Code {
+    // Lexer
+    UnexpectedCharacter,
+    // Parser
+    ExpectedTerminator,
+    ExpectedTerminatorAfterVar,
+    ExpectedTerminatorAfterReturn,
+    ExpectedTerminatorAfterThrow,
+    ExpectedTerminatorAfterImport,
+    ExpectedTerminatorAfterForCondition,
+    ExpectedLeftParenAfter,
+    ExpectedRightParenAfterCondition,
+    ExpectedRightParenAfterForClauses,
+    ExpectedRightParenAfterParams,
+    ExpectedRightParenAfterArguments,
+    ExpectedRightParenAfterExpression,
+    ExpectedRightParenAfterErrorVariable,
+    ExpectedLeftBraceAfter,
+    ExpectedLambdaBody,
+    ExpectedRightBrace,
+    ExpectedVariableName,
+    ExpectedParameterName,
+    ExpectedErrorVariableName,
+    ExpectedImportPath,
+    ExpectedFunctionName,
+    ExpectedLeftParenAfterFunctionName,
+    ExpectedFunctionBody,
+    ExportNotAtTopLevel,
+    ExpectedExportDeclaration,
+    TooManyParameters,
+    TooManyArguments,
+    ExpectedCatchOrFinally,
+    InvalidAssignmentTarget,
+    ExpectedExpression,
+    TooMuchNesting,
+    TooManyErrors,
+    ReturnOutsideFunction,
+    // Runtime
+    UndefinedVariable,
+    ConcatenationTypeMismatch,
+    OperandsMustBeNumbers,
+    NotANumber,
+    NotCallable,
+    ArityMismatch,
+    ModuleUnreadable,
+    ModuleSyntaxErrors,
+    ModuleNotFound,
+    ImportCycle,
+    NotAnError,
+    StackOverflow,
+    StepLimit,
+    Timeout,
+    StringTooLong,
+    AllocationLimit,
+    OutputLimit,
+    PermissionDenied,
+    CommandFailed,
+    NotText,
+    FileError,
+    // Translator
+    UnmatchedDedent,
+    UnsupportedRangeStep,
+    UnsupportedForLoop,
+}
+
+const CODES: &[(Code, &str)] = &[
+    (Code::UnexpectedCharacter, "E001"),
+    (Code::ExpectedTerminator, "E010"),
+    (Code::ExpectedTerminatorAfterVar, "E011"),
+    (Code::ExpectedTerminatorAfterReturn, "E012"),
+    (Code::ExpectedTerminatorAfterThrow, "E013"),
+    (Code::ExpectedTerminatorAfterImport, "E014"),
+    (Code::ExpectedTerminatorAfterForCondition, "E015"),
+    (Code::ExpectedLeftParenAfter, "E016"),
+    (Code::ExpectedRightParenAfterCondition, "E017"),
+    (Code::ExpectedRightParenAfterForClauses, "E018"),
+    (Code::ExpectedRightParenAfterParams, "E019"),
+    (Code::ExpectedRightParenAfterArguments, "E020"),
+    (Code::ExpectedRightParenAfterExpression, "E021"),
+    (Code::ExpectedRightParenAfterErrorVariable, "E022"),
+    (Code::ExpectedLeftBraceAfter, "E023"),
+    (Code::ExpectedLambdaBody, "E024"),
+    (Code::ExpectedRightBrace, "E025"),
+    (Code::ExpectedVariableName, "E026"),
+    (Code::ExpectedParameterName, "E027"),
+    (Code::ExpectedErrorVariableName, "E028"),
+    (Code::ExpectedImportPath, "E029"),
+    (Code::ExpectedFunctionName, "E030"),
+    (Code::ExpectedLeftParenAfterFunctionName, "E031"),
+    (Code::ExpectedFunctionBody, "E032"),
+    (Code::ExportNotAtTopLevel, "E033"),
+    (Code::ExpectedExportDeclaration, "E034"),
+    (Code::TooManyParameters, "E035"),
+    (Code::TooManyArguments, "E036"),
+    (Code::ExpectedCatchOrFinally, "E037"),
+    (Code::InvalidAssignmentTarget, "E038"),
+    (Code::ExpectedExpression, "E039"),
+    (Code::TooMuchNesting, "E040"),
+    (Code::TooManyErrors, "E041"),
+    (Code::ReturnOutsideFunction, "E042"),
+    (Code::UndefinedVariable, "E050"),
+    (Code::ConcatenationTypeMismatch, "E051"),
+    (Code::OperandsMustBeNumbers, "E052"),
+    (Code::NotANumber, "E053"),
+    (Code::NotCallable, "E054"),
+    (Code::ArityMismatch, "E055"),
+    (Code::ModuleUnreadable, "E056"),
+    (Code::ModuleSyntaxErrors, "E057"),
+    (Code::ModuleNotFound, "E058"),
+    (Code::ImportCycle, "E059"),
+    (Code::NotAnError, "E060"),
+    (Code::StackOverflow, "E061"),
+    (Code::StepLimit, "E062"),
+    (Code::Timeout, "E063"),
+    (Code::StringTooLong, "E064"),
+    (Code::AllocationLimit, "E065"),
+    (Code::OutputLimit, "E066"),
+    (Code::PermissionDenied, "E067"),
+    (Code::CommandFailed, "E068"),
+    (Code::NotText, "E069"),
+    (Code::FileError, "E070"),
+    (Code::UnmatchedDedent, "E080"),
+    (Code::UnsupportedRangeStep, "E081"),
+    (Code::UnsupportedForLoop, "E082"),
+];
+
+impl Code {
+    pub fn as_str(self) -> &'static str {
+        CODES
+            .iter()
+            .find(|(code, _)| *code == self)
+            .map(|(_, number)| *number)
+            .expect("every code has a number")
+    }
+
+    /// Whether the error is for going past an `InterpreterConfig` limit. A `🥅` can't catch
+    /// these, or code could keep going past its limits by catching them.
+    pub fn is_limit(self) -> bool {
+        matches!(
+            self,
+            Code::StepLimit | Code::Timeout | Code::StringTooLong | Code::AllocationLimit | Code::OutputLimit
+        )
+    }
+
+    /// The long-form explanation `emoji-lang --explain` prints, in the current locale
+    pub fn explanation(self) -> &'static str {
+        lookup(|catalog| &catalog.explanations, self.as_str()).unwrap_or("")
+    }
+}
+
+impl FromStr for Code {
+    type Err = String;
+
+    /// Parses `E050`, and also `e050` or `50`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s.trim_start_matches(['E', 'e']).parse::<u32>().ok();
+        let normalized = number.map(|number| format!("E{:03}", number));
+        CODES
+            .iter()
+            .find(|(_, code)| normalized.as_deref() == Some(*code))
+            .map(|(code, _)| *code)
+            .ok_or_else(|| format!("Unknown error code '{}'", s))
+    }
+}
+
+impl Display for Code {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// An error code along with the values its message mentions, rendered in the current locale
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    pub code: Code,
+    pub args: Vec<String>,
+    // A hint on how to fix the error, like a "did you mean" suggestion
+    pub help: Option<String>,
+    // Extra context shown under the error, like why a limit was reached
+    pub notes: Vec<String>,
+}
+
+impl Message {
+    pub fn new(code: Code) -> Self {
+        Self {
+            code,
+            args: Vec::new(),
+            help: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Adds the value for the next `{n}` placeholder
+    pub fn arg(mut self, arg: impl Display) -> Self {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    pub fn with_help(mut self, help: Option<String>) -> Self {
+        self.help = help;
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Display) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+}
+
+impl From<Code> for Message {
+    fn from(code: Code) -> Self {
+        Self::new(code)
+    }
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let template = lookup(|catalog| &catalog.messages, self.code.as_str());
+        write!(f, "{}", fill(template.unwrap_or(self.code.as_str()), &self.args))
+    }
+}
+
+/// Words diagnostics are framed with, like the `error` in `error[E010]: ...`
+#[derive(Clone, Copy, Debug)]
+pub enum Word {
+    Error,
+    Warning,
+    Note,
+    Help,
+    OpenedHere,
+    DidYouMean,
+    StackTrace,
+    CalledAt,
+    Repeated,
+    TailCalls,
+    StackSize,
+    Grant,
+}
+
+impl Word {
+    fn key(self) -> &'static str {
+        match self {
+            Word::Error => "error",
+            Word::Warning => "warning",
+            Word::Note => "note",
+            Word::Help => "help",
+            Word::OpenedHere => "opened_here",
+            Word::DidYouMean => "did_you_mean",
+            Word::StackTrace => "stack_trace",
+            Word::CalledAt => "called_at",
+            Word::Repeated => "repeated",
+            Word::TailCalls => "tail_calls",
+            Word::StackSize => "stack_size",
+            Word::Grant => "grant",
+        }
+    }
+}
+
+/// Renders a word in the current locale
+pub fn word(word: Word) -> &'static str {
+    lookup(|catalog| &catalog.words, word.key()).unwrap_or(word.key())
+}
+
+/// Finds `key` in the current locale's catalog, falling back to English
+fn lookup(section: fn(&Catalog) -> &HashMap<String, String>, key: &str) -> Option<&'static str> {
+    section(catalog(locale()))
+        .get(key)
+        .or_else(|| section(catalog(Locale::En)).get(key))
+        .map(String::as_str)
+}
+
+/// Renders a word with `{n}` placeholders, like `did you mean {0}?`, in the current locale
+pub fn word_with(template: Word, args: &[String]) -> String {
+    fill(word(template), args)
+}
+
+/// The "did you mean" hint for a suggested spelling, in the current locale
+pub fn did_you_mean(suggestion: impl Display) -> String {
+    word_with(Word::DidYouMean, &[suggestion.to_string()])
+}
+
+fn fill(template: &str, args: &[String]) -> String {
+    let mut result = template.to_string();
+    for (i, arg) in args.iter().enumerate() {
+        result = result.replace(&format!("{{{}}}", i), arg);
+    }
+    result
+}
+
+/// The language error messages are written in. `Emoji` explains errors with glyphs only.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Locale {
+    En,
+    Es,
+    Fr,
+    Pt,
+    Emoji,
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// Accepts `es`, and also `es_ES.UTF-8`-style values from the environment
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s.split(['_', '-', '.']).next().unwrap_or(s);
+        match language.to_lowercase().as_str() {
+            "en" => Ok(Locale::En),
+            "es" => Ok(Locale::Es),
+            "fr" => Ok(Locale::Fr),
+            "pt" => Ok(Locale::Pt),
+            "emoji" => Ok(Locale::Emoji),
+            _ => Err(format!("Unknown locale '{}', expected en, es, fr, pt or emoji", s)),
+        }
+    }
+}
+
+/// The environment variable that picks a locale when no `--locale` flag is given
+pub const LOCALE_VAR: &str = "EMOJI_LANG_LOCALE";
+
+static LOCALE: Mutex<Locale> = Mutex::new(Locale::En);
+
+/// Picks the locale from a `--locale` flag, falling back to `EMOJI_LANG_LOCALE`, then English
+pub fn init_locale(flag: Option<Locale>) {
+    let from_env = std::env::var(LOCALE_VAR).ok().and_then(|value| {
+        value
+            .parse()
+            .inspect_err(|e| eprintln!("⚠️ Ignoring {}: {}", LOCALE_VAR, e))
+            .ok()
+    });
+    set_locale(flag.or(from_env).unwrap_or(Locale::En));
+}
+
+pub fn set_locale(locale: Locale) {
+    *LOCALE.lock().unwrap_or_else(|e| e.into_inner()) = locale;
+}
+
+pub fn locale() -> Locale {
+    *LOCALE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Catalog {
+    #[serde(default)]
+    words: HashMap<String, String>,
+    #[serde(default)]
+    messages: HashMap<String, String>,
+    #[serde(default)]
+    explanations: HashMap<String, String>,
+}
+
+static CATALOGS: LazyLock<HashMap<Locale, Catalog>> = LazyLock::new(|| {
+    [
+        (Locale::En, include_str!("../messages/en.toml")),
+        (Locale::Es, include_str!("../messages/es.toml")),
+        (Locale::Fr, include_str!("../messages/fr.toml")),
+        (Locale::Pt, include_str!("../messages/pt.toml")),
+        (Locale::Emoji, include_str!("../messages/emoji.toml")),
+    ]
+    .into_iter()
+    .map(|(locale, contents)| {
+        let catalog = toml::from_str(contents).expect("the built-in message catalogs are valid");
+        (locale, catalog)
+    })
+    .collect()
+});
+
+fn catalog(locale: Locale) -> &'static Catalog {
+    &CATALOGS[&locale]
+}
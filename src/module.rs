@@ -0,0 +1,56 @@
+use crate::message::{Code, Message};
+use crate::token::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// The names a module marked with `📤`, and their values once it finished running
+pub type Exports = Rc<Vec<(String, Value)>>;
+
+/// Tracks every `.emoji` file loaded by `📦`, so each one runs at most once
+#[derive(Default)]
+pub struct Modules {
+    cache: HashMap<PathBuf, Exports>,
+    // Modules that are still running, outermost first
+    loading: Vec<PathBuf>,
+}
+
+impl Modules {
+    /// Resolves `path` relative to the directory of the file that imports it
+    pub fn resolve(importer: &str, path: &str) -> Result<PathBuf, Message> {
+        let dir = Path::new(importer).parent().unwrap_or(Path::new(""));
+        let joined = dir.join(path);
+        fs::canonicalize(&joined)
+            .map_err(|e| Message::new(Code::ModuleNotFound).arg(joined.display()).arg(e))
+    }
+
+    pub fn cached(&self, path: &Path) -> Option<Exports> {
+        self.cache.get(path).cloned()
+    }
+
+    /// Marks `path` as running, failing if it is already part of the import chain
+    pub fn start(&mut self, path: PathBuf) -> Result<(), Message> {
+        if let Some(index) = self.loading.iter().position(|loading| loading == &path) {
+            let cycle = self.loading[index..]
+                .iter()
+                .chain([&path])
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" ➡️ ");
+            return Err(Message::new(Code::ImportCycle).arg(cycle));
+        }
+        self.loading.push(path);
+        Ok(())
+    }
+
+    pub fn abort(&mut self) {
+        self.loading.pop();
+    }
+
+    pub fn finish(&mut self, exports: Exports) {
+        if let Some(path) = self.loading.pop() {
+            self.cache.insert(path, exports);
+        }
+    }
+}
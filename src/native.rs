@@ -0,0 +1,202 @@
+use crate::function::Callable;
+use crate::interpreter::Interpreter;
+use crate::message::{Code, Message};
+use crate::runtime_error::{throw, RuntimeError};
+use crate::token::{Token, Value};
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+
+pub type NativeFn = fn(&mut Interpreter, Vec<Value>) -> Result<Value, Message>;
+
+/// A built-in function implemented in Rust. An `Err` becomes a catchable runtime
+/// error reported at the call site.
+#[derive(Debug)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: usize,
+    pub function: NativeFn,
+}
+
+impl NativeFunction {
+    pub const fn new(name: &'static str, arity: usize, function: NativeFn) -> Self {
+        Self {
+            name,
+            arity,
+            function,
+        }
+    }
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, paren: &Token, args: Vec<Value>) -> Value {
+        (self.function)(interpreter, args)
+            .unwrap_or_else(|message| throw(RuntimeError::new(paren.span.clone(), message)))
+    }
+}
+
+impl Display for NativeFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+/// Built-ins every program starts with
+pub fn globals() -> Vec<NativeFunction> {
+    vec![
+        NativeFunction::new("errorMessage", 1, error_message),
+        NativeFunction::new("errorLine", 1, error_line),
+        NativeFunction::new("errorColumn", 1, error_column),
+        NativeFunction::new("getEnv", 1, get_env),
+        NativeFunction::new("runCommand", 1, run_command),
+    ]
+}
+
+/// Built-ins for reading and writing files, defined only when the file system is enabled.
+/// They still need `--allow-read` or `--allow-write` for the paths they touch.
+pub fn file_system() -> Vec<NativeFunction> {
+    vec![
+        NativeFunction::new("readFile", 1, read_file),
+        NativeFunction::new("writeFile", 2, write_file),
+        NativeFunction::new("appendFile", 2, append_file),
+        NativeFunction::new("listDir", 1, list_dir),
+        NativeFunction::new("fileExists", 1, file_exists),
+        NativeFunction::new("deleteFile", 1, delete_file),
+    ]
+}
+
+fn as_error(value: &Value) -> Result<&RuntimeError, Message> {
+    match value {
+        Value::Error(error) => Ok(error),
+        other => Err(Message::new(Code::NotAnError).arg(other)),
+    }
+}
+
+fn error_message(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, Message> {
+    Ok(Value::Text(as_error(&args[0])?.message.clone()))
+}
+
+fn error_line(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, Message> {
+    Ok(Value::Number(as_error(&args[0])?.location().line as f64))
+}
+
+fn error_column(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, Message> {
+    Ok(Value::Number(as_error(&args[0])?.location().column as f64))
+}
+
+fn as_text(value: &Value) -> Result<&str, Message> {
+    match value {
+        Value::Text(text) => Ok(text),
+        other => Err(Message::new(Code::NotText).arg(other)),
+    }
+}
+
+// The value of an environment variable, or nil if it isn't set
+fn get_env(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Message> {
+    let name = as_text(&args[0])?;
+    interpreter.permissions().check_env(name)?;
+    match env::var(name) {
+        Ok(value) => {
+            interpreter.allocate_text(value.len())?;
+            Ok(Value::Text(value))
+        }
+        Err(_) => Ok(Value::Nil),
+    }
+}
+
+// Runs a program with the arguments that follow it, split at spaces, and returns what it
+// printed, without the final line break
+fn run_command(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Message> {
+    let command = as_text(&args[0])?;
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    interpreter.permissions().check_run(program)?;
+    let failed = |reason: String| Message::new(Code::CommandFailed).arg(command).arg(reason);
+    let output = process::Command::new(program)
+        .args(words)
+        .output()
+        .map_err(|e| failed(e.to_string()))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = format!("{} {}", output.status, stderr.trim_end());
+        return Err(failed(reason.trim_end().to_string()));
+    }
+    let mut stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if stdout.ends_with('\n') {
+        stdout.pop();
+    }
+    interpreter.allocate_text(stdout.len())?;
+    Ok(Value::Text(stdout))
+}
+
+fn io_failed(function: &str, path: &str, error: std::io::Error) -> Message {
+    Message::new(Code::FileError).arg(function).arg(path).arg(error)
+}
+
+fn read_file(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Message> {
+    let path = as_text(&args[0])?;
+    interpreter.permissions().check_read(Path::new(path))?;
+    let contents = fs::read_to_string(path).map_err(|e| io_failed("readFile", path, e))?;
+    interpreter.allocate_text(contents.len())?;
+    Ok(Value::Text(contents))
+}
+
+// Writes the value as `🖨` would show it, without a line break, replacing what the file held
+fn write_file(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Message> {
+    let path = as_text(&args[0])?;
+    interpreter.permissions().check_write(Path::new(path))?;
+    fs::write(path, args[1].to_string()).map_err(|e| io_failed("writeFile", path, e))?;
+    Ok(Value::Nil)
+}
+
+// Like `writeFile`, but adds to the end of the file, creating it if needed
+fn append_file(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Message> {
+    let path = as_text(&args[0])?;
+    interpreter.permissions().check_write(Path::new(path))?;
+    fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(args[1].to_string().as_bytes()))
+        .map_err(|e| io_failed("appendFile", path, e))?;
+    Ok(Value::Nil)
+}
+
+// The names in a directory in alphabetical order, one per line, since there are no lists yet
+fn list_dir(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Message> {
+    let path = as_text(&args[0])?;
+    interpreter.permissions().check_read(Path::new(path))?;
+    let mut names = fs::read_dir(path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| io_failed("listDir", path, e))?;
+    names.sort();
+    let listing = names.join("\n");
+    interpreter.allocate_text(listing.len())?;
+    Ok(Value::Text(listing))
+}
+
+fn file_exists(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Message> {
+    let path = as_text(&args[0])?;
+    interpreter.permissions().check_read(Path::new(path))?;
+    let exists = Path::new(path).try_exists().map_err(|e| io_failed("fileExists", path, e))?;
+    Ok(Value::Boolean(exists))
+}
+
+// Deletes a file, but not a directory
+fn delete_file(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Message> {
+    let path = as_text(&args[0])?;
+    interpreter.permissions().check_write(Path::new(path))?;
+    fs::remove_file(path).map_err(|e| io_failed("deleteFile", path, e))?;
+    Ok(Value::Nil)
+}
@@ -1,29 +1,73 @@
 use crate::ast::{Expr, Stmt};
-use crate::error::{error_at, error_at_token};
+use crate::diagnostic::Diagnostic;
+use crate::error::{error_at, error_at_token, report_err};
+use crate::message::{word, Code, Message, Word};
 use crate::token::TokenType::{
-    And, Bang, BangEqual, Comma, TextConcat, Else, EndOfExpression, Equal, EqualEqual, False, For,
-    Function, Greater, GreaterEqual, Identifier, If, LeftCurlyBrace, LeftParen, Less, LessEqual,
-    Minus, Nil, Number, Or, Plus, Print, Return, RightCurlyBrace, RightParen, Slash, Star, Text,
-    True, Var, While,
+    And, Arrow, Bang, BangEqual, Catch, Comma, TextConcat, Else, EndOfExpression, Equal,
+    EqualEqual, Export, False, Finally, For, Function, Greater, GreaterEqual, Identifier, If,
+    Import, LeftCurlyBrace, LeftParen, Less, LessEqual, Minus, Newline, Nil, Number, Or, Plus,
+    Print, Return, RightCurlyBrace, RightParen, Slash, Star, Text, Throw, True, Try, Var, While,
 };
 use crate::token::Value;
 use crate::token::{Token, TokenType};
-use anyhow::Result;
+use anyhow::{bail, Result};
+
+// Deeply nested input would otherwise overflow the native stack while parsing
+const MAX_NESTING: usize = 256;
+// Past this many syntax errors, the rest are most likely knock-on effects of earlier ones
+const MAX_ERRORS: usize = 25;
 
 pub struct Parser {
     tokens: Vec<Token>,
+    // Whether a line break ends the statement before the token at the same index
+    line_breaks: Vec<bool>,
     current: usize,
+    depth: usize,
+    // How many `🫸` blocks the parser is inside, so recovery stops before their `🫷`
+    blocks: usize,
+    errors: usize,
+    // How many function bodies the parser is inside, so a `🔙` outside of one is an error
+    functions: usize,
+    // Set once `MAX_ERRORS` is reached; everything left unparsed is skipped
+    gave_up: bool,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+    pub fn new(raw_tokens: Vec<Token>) -> Self {
+        let mut tokens = Vec::with_capacity(raw_tokens.len());
+        let mut line_breaks = Vec::with_capacity(raw_tokens.len());
+        let mut line_break = false;
+        for token in raw_tokens {
+            if token.token_type == Newline {
+                line_break = true;
+            } else {
+                tokens.push(token);
+                line_breaks.push(line_break);
+                line_break = false;
+            }
+        }
+        if tokens.last().is_none_or(|token| token.token_type != TokenType::EndOfFile) {
+            let end = tokens.last().map_or(0, |token| token.span.end);
+            tokens.push(Token::new(TokenType::EndOfFile, String::new(), None, end..end));
+            line_breaks.push(line_break);
+        }
         let current = 0;
-        Self { tokens, current }
+        let depth = 0;
+        Self {
+            tokens,
+            line_breaks,
+            current,
+            depth,
+            blocks: 0,
+            errors: 0,
+            functions: 0,
+            gave_up: false,
+        }
     }
 
     pub fn parse(&mut self) -> Vec<Stmt> {
         let mut statements = Vec::new();
-        while !self.is_at_end() {
+        while !self.is_at_end() && !self.gave_up {
             if let Some(stmt) = self.declaration() {
                 statements.push(stmt);
             }
@@ -32,8 +76,13 @@ impl Parser {
     }
 
     fn declaration(&mut self) -> Option<Stmt> {
-        let result = if self.matches(&[Function]) {
-            self.function("function")
+        let start = self.current;
+        // A `🤖` without a name right after it starts an anonymous function expression instead
+        let result = if self.matches(&[Export]) {
+            self.export_declaration()
+        } else if self.check(&Function) && self.check_next(&Identifier) {
+            self.advance();
+            self.function()
         } else if self.matches(&[Var]) {
             self.var_declaration()
         } else {
@@ -42,56 +91,105 @@ impl Parser {
 
         match result {
             Ok(stmt) => Some(stmt),
+            Err(_) if self.gave_up => None,
             Err(_) => {
-                self.synchronize();
+                self.errors += 1;
+                if self.errors >= MAX_ERRORS {
+                    error_at_token(&self.peek(), Message::new(Code::TooManyErrors).arg(MAX_ERRORS));
+                    self.gave_up = true;
+                } else {
+                    self.synchronize(start);
+                }
                 None
             }
         }
     }
 
-    fn function(&mut self, kind: &str) -> Result<Stmt> {
-        let name = self.consume(&Identifier, format!("Expected {} name", kind).as_str())?;
-        self.consume(
-            &LeftParen,
-            format!("Expected '🫱' after {} name", kind).as_str(),
-        )?;
+    fn export_declaration(&mut self) -> Result<Stmt> {
+        let keyword = self.previous();
+        if self.depth > 0 {
+            return error_at(keyword, Code::ExportNotAtTopLevel);
+        }
+        let declaration = if self.matches(&[Var]) {
+            self.var_declaration()?
+        } else if self.check(&Function) && self.check_next(&Identifier) {
+            self.advance();
+            self.function()?
+        } else {
+            return error_at(self.peek(), Code::ExpectedExportDeclaration);
+        };
+        Ok(Stmt::export(keyword, declaration))
+    }
+
+    fn function(&mut self) -> Result<Stmt> {
+        let name = self.consume(&Identifier, Code::ExpectedFunctionName)?;
+        self.consume(&LeftParen, Code::ExpectedLeftParenAfterFunctionName)?;
+        let params = self.parameters()?;
+        self.consume(&LeftCurlyBrace, Code::ExpectedFunctionBody)?;
+        let body = self.function_body()?;
+        Ok(Stmt::func(name, params, body))
+    }
+
+    fn lambda(&mut self) -> Result<Expr> {
+        let keyword = self.previous();
+        self.consume(&LeftParen, Message::new(Code::ExpectedLeftParenAfter).arg("🤖"))?;
+        let params = self.parameters()?;
+        let body = if self.matches(&[Arrow]) {
+            // `🤖🫱x🫲👉x✨x` is short for `🤖🫱x🫲🫸🔙x✨x✊🫷`
+            let arrow = self.previous();
+            let value = self.expression()?;
+            vec![Stmt::return_(arrow, Some(value))]
+        } else {
+            self.consume(&LeftCurlyBrace, Code::ExpectedLambdaBody)?;
+            self.function_body()?
+        };
+        Ok(Expr::lambda(keyword, params, body))
+    }
+
+    // Parses the `🫸` block of a function, after its opening `🫸`
+    fn function_body(&mut self) -> Result<Vec<Stmt>> {
+        self.functions += 1;
+        let body = self.nested(Self::block);
+        self.functions -= 1;
+        body
+    }
+
+    // Parses a parameter list up to and including the closing `🫲`
+    fn parameters(&mut self) -> Result<Vec<Token>> {
         let mut params = Vec::new();
         if !self.check(&RightParen) {
             // first parameter
-            params.push(self.consume(&Identifier, "Expected parameter name")?);
+            params.push(self.consume(&Identifier, Code::ExpectedParameterName)?);
 
             // remaining parameters
             while self.matches(&[Comma]) {
                 if params.len() >= 255 {
-                    error_at_token(&self.peek(), "Can't have more than 255 params");
+                    error_at_token(&self.peek(), Code::TooManyParameters);
                 }
-                params.push(self.consume(&Identifier, "Expected parameter name")?);
+                params.push(self.consume(&Identifier, Code::ExpectedParameterName)?);
             }
         }
-        self.consume(&RightParen, "Expected '🫲' after params")?;
-        self.consume(
-            &LeftCurlyBrace,
-            format!("Expected '🫸' before {} body", kind).as_str(),
-        )?;
-        let body = self.block()?;
-        Ok(Stmt::func(name, params, body))
+        self.consume(&RightParen, Code::ExpectedRightParenAfterParams)?;
+        Ok(params)
     }
 
     fn var_declaration(&mut self) -> Result<Stmt> {
-        let name = self.consume(&Identifier, "Expected a variable name")?;
-        let initializer = self
-            .matches(&[Equal])
-            .then(|| self.expression().ok())
-            .flatten();
-
-        self.consume(
-            &EndOfExpression,
-            "Expected '✊' at the end of variable declaration",
-        )?;
+        let name = self.consume(&Identifier, Code::ExpectedVariableName)?;
+        let initializer = if self.matches(&[Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume_terminator(Code::ExpectedTerminatorAfterVar)?;
         Ok(Stmt::Var(name, initializer))
     }
 
     fn statement(&mut self) -> Result<Stmt> {
+        self.nested(Self::statement_inner)
+    }
+
+    fn statement_inner(&mut self) -> Result<Stmt> {
         if self.matches(&[For]) {
             self.for_statement()
         } else if self.matches(&[If]) {
@@ -102,6 +200,12 @@ impl Parser {
             self.return_statement()
         } else if self.matches(&[While]) {
             self.while_statement()
+        } else if self.matches(&[Throw]) {
+            self.throw_statement()
+        } else if self.matches(&[Try]) {
+            self.try_statement()
+        } else if self.matches(&[Import]) {
+            self.import_statement()
         } else if self.matches(&[LeftCurlyBrace]) {
             self.block_statement()
         } else {
@@ -110,31 +214,34 @@ impl Parser {
     }
 
     fn for_statement(&mut self) -> Result<Stmt> {
-        self.consume(&LeftParen, "Expected '🫱' after '⏳'")?;
+        let keyword = self.previous();
+        self.consume(&LeftParen, Message::new(Code::ExpectedLeftParenAfter).arg("⏳"))?;
 
         let initializer = match () {
             _ if self.matches(&[EndOfExpression]) => None,
-            _ if self.matches(&[Var]) => self.var_declaration().ok(),
-            _ => self.expression_statement().ok(),
+            _ if self.matches(&[Var]) => Some(self.var_declaration()?),
+            _ => Some(self.expression_statement()?),
         };
 
         let condition = if self.check(&EndOfExpression) {
-            Expr::literal(Value::Boolean(true))
+            Expr::literal(self.peek(), Value::Boolean(true))
         } else {
             self.expression()?
         };
-        self.consume(&EndOfExpression, "Expected '✊' after '⏳' loop condition")?;
+        self.consume(&EndOfExpression, Code::ExpectedTerminatorAfterForCondition)?;
 
-        let increment = (!self.check(&RightParen))
-            .then(|| self.expression().ok())
-            .flatten();
-        self.consume(&RightParen, "Expected '🫲' after '⏳' clause")?;
+        let increment = if self.check(&RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(&RightParen, Code::ExpectedRightParenAfterForClauses)?;
 
         let mut body = self.statement()?;
         if let Some(inc) = increment {
             body = Stmt::block(vec![body, Stmt::expression(inc)])
         }
-        body = Stmt::while_(condition, body);
+        body = Stmt::while_(keyword, condition, body);
         if let Some(init) = initializer {
             body = Stmt::block(vec![init, body])
         }
@@ -143,38 +250,88 @@ impl Parser {
     }
 
     fn if_statement(&mut self) -> Result<Stmt> {
-        self.consume(&LeftParen, "Expected '🫱' after '🤔'")?;
+        self.consume(&LeftParen, Message::new(Code::ExpectedLeftParenAfter).arg("🤔"))?;
 
         let condition = self.expression()?;
-        self.consume(&RightParen, "Expected '🫲' after '🤔' condition")?;
+        self.consume(&RightParen, Message::new(Code::ExpectedRightParenAfterCondition).arg("🤔"))?;
 
         let then_branch = self.statement()?;
-        let else_branch = self
-            .matches(&[Else])
-            .then(|| self.statement().ok())
-            .flatten();
+        let else_branch = if self.matches(&[Else]) {
+            Some(self.statement()?)
+        } else {
+            None
+        };
         Ok(Stmt::if_(condition, then_branch, else_branch))
     }
 
     fn print_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous();
         let expr = self.expression()?;
-        self.consume(&EndOfExpression, "Expected '✊' after value")?;
-        Ok(Stmt::print(expr))
+        self.consume_terminator(Code::ExpectedTerminator)?;
+        Ok(Stmt::print(keyword, expr))
     }
 
     fn return_statement(&mut self) -> Result<Stmt> {
         let keyword = self.previous();
-        let value = (!self.check(&EndOfExpression)).then(|| self.expression().ok()).flatten();
-        self.consume(&EndOfExpression, "Expected '✊' after return value")?;
+        if self.functions == 0 {
+            error_at_token(&keyword, Code::ReturnOutsideFunction);
+        }
+        let value = if self.at_terminator() {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume_terminator(Code::ExpectedTerminatorAfterReturn)?;
         Ok(Stmt::return_(keyword, value))
     }
 
     fn while_statement(&mut self) -> Result<Stmt> {
-        self.consume(&LeftParen, "Expected '🫱' after '🌀'")?;
+        let keyword = self.previous();
+        self.consume(&LeftParen, Message::new(Code::ExpectedLeftParenAfter).arg("🌀"))?;
         let condition = self.expression()?;
-        self.consume(&RightParen, "Expected '🫲' after '🌀' condition")?;
+        self.consume(&RightParen, Message::new(Code::ExpectedRightParenAfterCondition).arg("🌀"))?;
         let body = self.statement()?;
-        Ok(Stmt::while_(condition, body))
+        Ok(Stmt::while_(keyword, condition, body))
+    }
+
+    fn import_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous();
+        let path = self.consume(&Text, Code::ExpectedImportPath)?;
+        self.consume_terminator(Code::ExpectedTerminatorAfterImport)?;
+        Ok(Stmt::import(keyword, path))
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume_terminator(Code::ExpectedTerminatorAfterThrow)?;
+        Ok(Stmt::throw(keyword, value))
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt> {
+        self.consume(&LeftCurlyBrace, Message::new(Code::ExpectedLeftBraceAfter).arg("🤞"))?;
+        let body = self.nested(Self::block)?;
+
+        let (mut catch_param, mut catch_body) = (None, None);
+        if self.matches(&[Catch]) {
+            if self.matches(&[LeftParen]) {
+                catch_param = Some(self.consume(&Identifier, Code::ExpectedErrorVariableName)?);
+                self.consume(&RightParen, Code::ExpectedRightParenAfterErrorVariable)?;
+            }
+            self.consume(&LeftCurlyBrace, Message::new(Code::ExpectedLeftBraceAfter).arg("🥅"))?;
+            catch_body = Some(self.nested(Self::block)?);
+        }
+
+        let mut finally_body = None;
+        if self.matches(&[Finally]) {
+            self.consume(&LeftCurlyBrace, Message::new(Code::ExpectedLeftBraceAfter).arg("🏁"))?;
+            finally_body = Some(self.nested(Self::block)?);
+        }
+
+        if catch_body.is_none() && finally_body.is_none() {
+            return error_at(self.peek(), Code::ExpectedCatchOrFinally);
+        }
+        Ok(Stmt::try_(body, catch_param, catch_body, finally_body))
     }
 
     fn block_statement(&mut self) -> Result<Stmt> {
@@ -182,24 +339,31 @@ impl Parser {
     }
 
     fn block(&mut self) -> Result<Vec<Stmt>> {
+        let open = self.previous();
         let mut stmts = Vec::new();
-        while !self.check(&RightCurlyBrace) && !self.is_at_end() {
+        self.blocks += 1;
+        while !self.check(&RightCurlyBrace) && !self.is_at_end() && !self.gave_up {
             if let Some(stmt) = self.declaration() {
                 stmts.push(stmt);
             }
         }
-        self.consume(&RightCurlyBrace, "Expected '🫷' after block")?;
+        self.blocks -= 1;
+        if self.gave_up {
+            // Unwind without reporting a missing `🫷`; the parser has stopped reporting errors
+            bail!("parsing stopped");
+        }
+        self.consume_closing(&RightCurlyBrace, &open, Code::ExpectedRightBrace)?;
         Ok(stmts)
     }
 
     fn expression_statement(&mut self) -> Result<Stmt> {
         let expr = self.expression()?;
-        self.consume(&EndOfExpression, "Expected '✊' after value")?;
+        self.consume_terminator(Code::ExpectedTerminator)?;
         Ok(Stmt::expression(expr))
     }
 
     fn expression(&mut self) -> Result<Expr> {
-        self.assignment()
+        self.nested(Self::assignment)
     }
 
     fn assignment(&mut self) -> Result<Expr> {
@@ -210,7 +374,7 @@ impl Parser {
             if let Expr::Variable(name) = expr {
                 Ok(Expr::assign(name, value))
             } else {
-                error_at(equals, "Invalid assignment target")
+                error_at(equals, Code::InvalidAssignmentTarget)
             }
         } else {
             Ok(expr)
@@ -282,7 +446,7 @@ impl Parser {
     fn unary(&mut self) -> Result<Expr> {
         if self.matches(&[Bang, Minus]) {
             let operator = self.previous();
-            let right = self.unary()?;
+            let right = self.nested(Self::unary)?;
             let expr = Expr::unary(operator, right);
             Ok(expr)
         } else {
@@ -303,12 +467,13 @@ impl Parser {
     }
 
     fn finish_call(&mut self, callee: Expr) -> Result<Expr> {
+        let open = self.previous();
         let mut arguments = Vec::new();
 
         if !self.check(&RightParen) {
             loop {
                 if arguments.len() >= 255 {
-                    error_at_token(&self.peek(), "Can't have more than 255 arguments");
+                    error_at_token(&self.peek(), Code::TooManyArguments);
                 }
                 arguments.push(self.expression()?);
                 if !self.matches(&[Comma]) {
@@ -317,49 +482,88 @@ impl Parser {
             }
         }
 
-        let paren = self.consume(&RightParen, "Expected a right hand `🫲` after arguments")?;
+        let paren = self.consume_closing(&RightParen, &open, Code::ExpectedRightParenAfterArguments)?;
         Ok(Expr::call(callee, paren, arguments))
     }
 
     fn primary(&mut self) -> Result<Expr> {
         if self.matches(&[False]) {
             let value = Value::Boolean(false);
-            return Ok(Expr::literal(value));
+            return Ok(Expr::literal(self.previous(), value));
         }
         if self.matches(&[True]) {
             let value = Value::Boolean(true);
-            return Ok(Expr::literal(value));
+            return Ok(Expr::literal(self.previous(), value));
         }
         if self.matches(&[Nil]) {
             let value = Value::Nil;
-            return Ok(Expr::literal(value));
+            return Ok(Expr::literal(self.previous(), value));
         }
         if self.matches(&[Number, Text]) {
-            let value = self.previous().value.unwrap_or_else(|| Value::Nil);
-            return Ok(Expr::literal(value));
+            let token = self.previous();
+            let value = token.value.clone().unwrap_or(Value::Nil);
+            return Ok(Expr::literal(token, value));
         }
         if self.matches(&[Identifier]) {
             return Ok(Expr::variable(self.previous()));
         }
+        if self.matches(&[Function]) {
+            return self.lambda();
+        }
         if self.matches(&[LeftParen]) {
+            let open = self.previous();
             let expr = self.expression()?;
-            self.consume(&RightParen, "Expected a right hand `🫲` after expression")?;
+            self.consume_closing(&RightParen, &open, Code::ExpectedRightParenAfterExpression)?;
             return Ok(Expr::grouping(expr));
         }
 
         // TODO: panic?
-        error_at(self.peek(), "Parse error in primary")
+        error_at(self.peek(), Code::ExpectedExpression)
     }
 
     // Helper functions
-    fn consume(&mut self, token_type: &TokenType, message: &str) -> Result<Token> {
+    fn nested<T>(&mut self, parse: fn(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_NESTING {
+            return error_at(self.peek(), Code::TooMuchNesting);
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn consume(&mut self, token_type: &TokenType, message: impl Into<Message>) -> Result<Token> {
+        if self.check(token_type) {
+            return Ok(self.advance());
+        }
+
+        error_at(self.peek(), message)
+    }
+
+    // Like `consume`, but the error also points back at the bracket being closed
+    fn consume_closing(&mut self, token_type: &TokenType, open: &Token, code: Code) -> Result<Token> {
         if self.check(token_type) {
             return Ok(self.advance());
         }
 
+        let diagnostic = Diagnostic::error(self.peek().span, code)
+            .with_label(open.span.clone(), word(Word::OpenedHere));
+        report_err(diagnostic)
+    }
+
+    // A statement ends at `✊`, or at a line break the lexer marked as a terminator
+    fn consume_terminator(&mut self, message: impl Into<Message>) -> Result<()> {
+        if self.matches(&[EndOfExpression]) || self.line_breaks[self.current] {
+            return Ok(());
+        }
+
         error_at(self.peek(), message)
     }
 
+    fn at_terminator(&self) -> bool {
+        self.check(&EndOfExpression) || self.line_breaks[self.current]
+    }
+
     // fn error(&self, token: Token, message: &str) -> Result<Token> {
     //     Err(anyhow!(
     //         "Parse error: \"{}\", got `{}`",
@@ -368,16 +572,47 @@ impl Parser {
     //     ))
     // }
 
-    fn synchronize(&mut self) {
-        self.advance();
+    // Skips the rest of a statement that failed to parse, so the next one can be parsed and
+    // its errors reported too. Blocks met on the way are still parsed for their own errors,
+    // and a `🫷` closing an enclosing block is left for that block.
+    fn synchronize(&mut self, start: usize) {
+        // Always make progress, unless the error was at a `🫷` the enclosing block consumes
+        if self.current == start && !self.closes_block() && self.advance().token_type == EndOfExpression {
+            return;
+        }
 
-        while !self.is_at_end() {
-            if self.previous().token_type == EndOfExpression {
-                return;
-            }
+        // A `✊` inside a `⏳🫱...🫲` header doesn't end the statement
+        let mut parens = self.tokens[start..self.current]
+            .iter()
+            .fold(0usize, |parens, token| match token.token_type {
+                LeftParen => parens + 1,
+                RightParen => parens.saturating_sub(1),
+                _ => parens,
+            });
+        while !self.is_at_end() && !self.line_breaks[self.current] {
             match self.peek().token_type {
-                // TODO: add class, for
-                Function | Var | If | While | Print | Return => return,
+                EndOfExpression if parens == 0 => {
+                    self.advance();
+                    return;
+                }
+                LeftParen => {
+                    parens += 1;
+                    self.advance();
+                }
+                RightParen => {
+                    parens = parens.saturating_sub(1);
+                    self.advance();
+                }
+                Function | Var | For | If | While | Print | Return | Throw | Try | Import | Export => {
+                    return;
+                }
+                LeftCurlyBrace => {
+                    self.advance();
+                    if self.nested(Self::block).is_err() {
+                        return;
+                    }
+                }
+                _ if self.closes_block() => return,
                 _ => {
                     self.advance();
                 }
@@ -385,6 +620,10 @@ impl Parser {
         }
     }
 
+    fn closes_block(&self) -> bool {
+        self.blocks > 0 && self.check(&RightCurlyBrace)
+    }
+
     /*
     TODO Implement this error logic and call it from fn error(...) above
       static void error(Token token, String message) {
@@ -414,6 +653,12 @@ impl Parser {
         }
     }
 
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|token| &token.token_type == token_type)
+    }
+
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
             self.current += 1;
@@ -426,10 +671,11 @@ impl Parser {
     }
 
     fn peek(&self) -> Token {
-        self.tokens[self.current].clone()
+        // `new` guarantees the stream ends with `EndOfFile`, and `advance` never moves past it
+        self.tokens[self.current.min(self.tokens.len() - 1)].clone()
     }
 
     fn previous(&self) -> Token {
-        self.tokens[self.current - 1].clone()
+        self.tokens[self.current.saturating_sub(1)].clone()
     }
 }
@@ -0,0 +1,138 @@
+use crate::message::{word_with, Code, Message, Word};
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// What one kind of access is granted for
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Allow {
+    #[default]
+    Nothing,
+    Everything,
+    /// Only these paths (and what's inside them), environment variables or programs
+    Only(Vec<String>),
+}
+
+impl Allow {
+    /// Reads an `--allow-...` flag: not given, given bare, or given a list like `--allow-read=a,b`
+    pub fn from_flag(values: Option<Vec<String>>) -> Self {
+        match values {
+            None => Allow::Nothing,
+            Some(values) if values.is_empty() => Allow::Everything,
+            Some(values) => Allow::Only(values),
+        }
+    }
+}
+
+/// The kinds of access the built-ins ask for, named like their `--allow-...` flags
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Env,
+    Run,
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Env => "env",
+            Access::Run => "run",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What code run by an `Interpreter` may touch outside of it. Built-ins that read files,
+/// write them, read environment variables or run programs check here first, and raise a
+/// "permission denied" error for anything not granted. Everything is denied by default.
+#[derive(Clone, Debug, Default)]
+pub struct Permissions {
+    pub read: Allow,
+    pub write: Allow,
+    pub env: Allow,
+    pub run: Allow,
+}
+
+impl Permissions {
+    /// Grants every kind of access, for code that is trusted
+    pub fn allow_all() -> Self {
+        Self {
+            read: Allow::Everything,
+            write: Allow::Everything,
+            env: Allow::Everything,
+            run: Allow::Everything,
+        }
+    }
+
+    pub fn check_read(&self, path: &Path) -> Result<(), Message> {
+        check_path(Access::Read, &self.read, path)
+    }
+
+    pub fn check_write(&self, path: &Path) -> Result<(), Message> {
+        check_path(Access::Write, &self.write, path)
+    }
+
+    pub fn check_env(&self, name: &str) -> Result<(), Message> {
+        check_name(Access::Env, &self.env, name)
+    }
+
+    pub fn check_run(&self, program: &str) -> Result<(), Message> {
+        check_name(Access::Run, &self.run, program)
+    }
+}
+
+fn check_path(access: Access, allow: &Allow, path: &Path) -> Result<(), Message> {
+    let granted = match allow {
+        Allow::Nothing => false,
+        Allow::Everything => true,
+        Allow::Only(allowed) => {
+            let path = resolve(path);
+            allowed.iter().any(|allowed| path.starts_with(resolve(Path::new(allowed))))
+        }
+    };
+    if granted { Ok(()) } else { Err(denied(access, path.display())) }
+}
+
+fn check_name(access: Access, allow: &Allow, name: &str) -> Result<(), Message> {
+    let granted = match allow {
+        Allow::Nothing => false,
+        Allow::Everything => true,
+        Allow::Only(allowed) => allowed.iter().any(|allowed| allowed == name),
+    };
+    if granted { Ok(()) } else { Err(denied(access, name)) }
+}
+
+fn denied(access: Access, target: impl Display) -> Message {
+    let flag = format!("--allow-{}", access);
+    Message::new(Code::PermissionDenied)
+        .arg(access)
+        .arg(target)
+        .with_help(Some(word_with(Word::Grant, &[flag])))
+}
+
+// The absolute path `path` names, with symlinks resolved so a link can't lead out of an
+// allowed directory. The part that doesn't exist yet, like a file about to be written, is
+// cleaned up without touching the file system.
+fn resolve(path: &Path) -> PathBuf {
+    let absolute = env::current_dir().unwrap_or_default().join(path);
+    for ancestor in absolute.ancestors() {
+        if let Ok(mut resolved) = fs::canonicalize(ancestor) {
+            let missing = absolute.strip_prefix(ancestor).unwrap_or(Path::new(""));
+            for component in missing.components() {
+                match component {
+                    Component::ParentDir => {
+                        resolved.pop();
+                    }
+                    Component::Normal(part) => resolved.push(part),
+                    _ => {}
+                }
+            }
+            return resolved;
+        }
+    }
+    absolute
+}
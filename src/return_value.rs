@@ -1,15 +1,37 @@
-use crate::token::Value;
+use crate::function::EmojiFunction;
+use crate::token::{Token, Value};
+use std::rc::Rc;
 
 #[derive(Debug)]
 pub struct Return {
     pub value: Value,
+    // Set by `🔙 f🫱...🫲`, where the function returning hands the call to `f` back to its
+    // caller instead of making it (see `EmojiFunction::call`)
+    pub tail_call: Option<TailCall>,
+}
+
+/// A call in tail position, with its arguments already evaluated
+#[derive(Debug)]
+pub struct TailCall {
+    pub function: Rc<EmojiFunction>,
+    pub paren: Token,
+    pub args: Vec<Value>,
 }
 
 impl Return {
     pub fn new(value: Value) -> Self {
-        Self { value }
+        Self {
+            value,
+            tail_call: None,
+        }
+    }
+    pub fn tail_call(function: Rc<EmojiFunction>, paren: Token, args: Vec<Value>) -> Self {
+        Self {
+            value: Value::Nil,
+            tail_call: Some(TailCall { function, paren, args }),
+        }
     }
 }
 
 // TODO - hacky, will fix later
-unsafe impl Send for Return {}
\ No newline at end of file
+unsafe impl Send for Return {}
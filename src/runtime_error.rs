@@ -0,0 +1,72 @@
+use crate::message::{Code, Message};
+use crate::source::{Location, locate};
+use crate::token::Token;
+use logos::Span;
+use std::fmt::{Display, Formatter};
+use std::panic::panic_any;
+
+/// An error raised while running emoji code, either by `🧨` or by the interpreter itself.
+/// Like `Return`, it travels up the Rust stack as a panic payload until a `🥅` catches it.
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    // Set for errors the interpreter raises itself, and unset for values thrown by `🧨`
+    pub code: Option<Code>,
+    pub message: String,
+    pub span: Span,
+    pub help: Option<String>,
+    pub notes: Vec<String>,
+    // The calls the error happened inside of, innermost first. Filled in once the error is
+    // caught or reaches the top, from the interpreter's call stack at that point.
+    pub trace: Vec<Frame>,
+}
+
+impl RuntimeError {
+    pub fn new(span: Span, message: impl Into<Message>) -> Self {
+        let message = message.into();
+        Self {
+            code: Some(message.code),
+            message: message.to_string(),
+            span,
+            help: message.help,
+            notes: message.notes,
+            trace: Vec::new(),
+        }
+    }
+    /// An error raised by `🧨` with any value, which becomes its message
+    pub fn thrown(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            code: None,
+            message: message.into(),
+            span,
+            help: None,
+            notes: Vec::new(),
+            trace: Vec::new(),
+        }
+    }
+    pub fn location(&self) -> Location {
+        locate(self.span.start)
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// One call to an emoji function: the name it was declared with, and where it was called from
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub function: Token,
+    pub call_site: Span,
+}
+
+impl Frame {
+    pub fn new(function: Token, call_site: Span) -> Self {
+        Self { function, call_site }
+    }
+}
+
+pub fn throw(error: RuntimeError) -> ! {
+    panic_any(error)
+}
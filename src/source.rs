@@ -0,0 +1,121 @@
+use crate::source_map::SourceMap;
+use logos::Span;
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+
+// Every loaded file gets its own range of offsets, so a token span alone is
+// enough to find the file, line and column it came from.
+static FILES: Mutex<Vec<SourceFile>> = Mutex::new(Vec::new());
+
+struct SourceFile {
+    name: String,
+    text: String,
+    base: usize,
+    // Set for translated files, so locations point into the text they came from
+    map: Option<SourceMap>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// Registers a file's contents and returns the offset its spans start at
+pub fn add_file(name: &str, text: &str) -> usize {
+    add(name, text, None)
+}
+
+/// Like `add_file`, but locations inside it are reported against the map's original text
+pub fn add_mapped_file(name: &str, text: &str, map: SourceMap) -> usize {
+    add(name, text, Some(map))
+}
+
+fn add(name: &str, text: &str, map: Option<SourceMap>) -> usize {
+    let mut files = FILES.lock().unwrap_or_else(|e| e.into_inner());
+    // Leave a gap of one so an end-of-file span never points into the next file
+    let base = files.last().map_or(0, |file| file.base + file.text.len() + 1);
+    files.push(SourceFile {
+        name: name.to_string(),
+        text: text.to_string(),
+        base,
+        map,
+    });
+    base
+}
+
+pub fn locate(offset: usize) -> Location {
+    with_text(offset, location_in).unwrap_or_else(|| Location {
+        file: String::from("<unknown>"),
+        line: 0,
+        column: 0,
+    })
+}
+
+/// The line a span starts on, to show under an error message
+#[derive(Clone, Debug, PartialEq)]
+pub struct Excerpt {
+    pub location: Location,
+    pub line: String,
+    // Where the span ends on that line, or just past the line if it runs onto the next one
+    pub end_column: usize,
+}
+
+pub fn excerpt(span: &Span) -> Option<Excerpt> {
+    let (location, line) = with_text(span.start, |name, text, local| {
+        let location = location_in(name, text, local);
+        let line = text.lines().nth(location.line - 1).unwrap_or("").to_string();
+        (location, line)
+    })?;
+    let end = locate(span.end);
+    let past_line = line.chars().count() + 1;
+    let end_column = if end.file == location.file && end.line == location.line {
+        end.column.clamp(location.column, past_line)
+    } else {
+        past_line
+    };
+    Some(Excerpt {
+        location,
+        line,
+        end_column,
+    })
+}
+
+// Calls `f` with the name and text of the file `offset` is in, and the offset inside it.
+// For translated files, that is the original text the source map points back to.
+fn with_text<T>(offset: usize, f: impl FnOnce(&str, &str, usize) -> T) -> Option<T> {
+    let files = FILES.lock().unwrap_or_else(|e| e.into_inner());
+    let file = files.iter().rev().find(|file| file.base <= offset)?;
+    let local = (offset - file.base).min(file.text.len());
+    let (name, text, local) = match &file.map {
+        Some(map) => (&map.source_name, &map.source_text, map.source_offset(local)),
+        None => (&file.name, &file.text, local),
+    };
+    Some(f(name, text, local.min(text.len())))
+}
+
+fn location_in(name: &str, text: &str, local: usize) -> Location {
+    let before = &text[..floor_char_boundary(text, local)];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+    Location {
+        file: name.to_string(),
+        line,
+        column,
+    }
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
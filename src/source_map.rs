@@ -0,0 +1,80 @@
+use std::fmt::{Display, Formatter};
+
+const HEADER: &str = "emoji-lang source map v1";
+
+/// Where one translated token starts in the original text and in the emoji output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mapping {
+    pub source: usize,
+    pub target: usize,
+}
+
+/// Links offsets in translated emoji code back to the text file it was translated from.
+/// Written by `emoji-translator --source-map` next to its output as `<output>.map`.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    pub source_name: String,
+    pub source_text: String,
+    // One entry per translated token, ordered by both offsets
+    pub mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    /// Maps an offset in the emoji code back to the start of the text token it came from
+    pub fn source_offset(&self, target: usize) -> usize {
+        let index = self.mappings.partition_point(|m| m.target <= target);
+        self.mappings
+            .get(index.saturating_sub(1))
+            .map_or(0, |mapping| mapping.source)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.splitn(4, '\n');
+        if lines.next() != Some(HEADER) {
+            return Err(format!("Expected a source map starting with '{}'", HEADER));
+        }
+        let source_name = lines
+            .next()
+            .and_then(|line| line.strip_prefix("source "))
+            .ok_or("Expected a 'source' line")?
+            .to_string();
+        let mappings = lines
+            .next()
+            .and_then(|line| line.strip_prefix("mappings"))
+            .ok_or("Expected a 'mappings' line")?
+            .split_whitespace()
+            .map(parse_mapping)
+            .collect::<Result<Vec<_>, _>>()?;
+        let source_text = lines.next().unwrap_or_default().to_string();
+        Ok(Self {
+            source_name,
+            source_text,
+            mappings,
+        })
+    }
+}
+
+fn parse_mapping(pair: &str) -> Result<Mapping, String> {
+    let (target, source) = pair
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid mapping '{}'", pair))?;
+    let parse = |n: &str| n.parse().map_err(|_| format!("Invalid mapping '{}'", pair));
+    Ok(Mapping {
+        target: parse(target)?,
+        source: parse(source)?,
+    })
+}
+
+// The original text goes last, verbatim, so it needs no escaping
+impl Display for SourceMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "source {}", self.source_name)?;
+        write!(f, "mappings")?;
+        for mapping in &self.mappings {
+            write!(f, " {}:{}", mapping.target, mapping.source)?;
+        }
+        writeln!(f)?;
+        write!(f, "{}", self.source_text)
+    }
+}
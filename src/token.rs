@@ -1,5 +1,8 @@
 use crate::function::{EmojiFunction};
+use crate::native::NativeFunction;
+use crate::runtime_error::RuntimeError;
 use logos::{Logos, Span};
+use serde::Deserialize;
 use std::fmt::{Display, Formatter, Result};
 use std::rc::Rc;
 
@@ -30,48 +33,32 @@ impl Display for Token {
     }
 }
 
-#[derive(Logos, Debug, PartialEq, Clone)]
-#[logos(skip r"[ \t\n\f]+")]
+// Keywords, operators and delimiters are spelled by the active `Dialect` (see
+// `dialects/default.toml`); logos only recognizes literals, comments and line breaks.
+#[derive(Logos, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[logos(skip r"[ \t\r\f]+")]
 pub enum TokenType {
-    // Operators - tokens
-    #[token("🫱")]
+    // Operators
     LeftParen,
-    #[token("🫲")]
     RightParen,
-    #[token("🫸")]
     LeftCurlyBrace,
-    #[token("🫷")]
     RightCurlyBrace,
-    #[token("🥂")]
     Plus,
-    #[token("🪡")]
     TextConcat,
-    #[token("💔")]
     Minus,
-    #[token("🔪")]
     Slash,
-    #[token("⚡️")]
     Percent,
-    #[token("✨")]
     Star,
-    #[token("👏")]
     Equal,
-    #[token("👏👏")]
     EqualEqual,
-    #[token("🙅‍♀️")]
     Bang,
-    #[token("🙅‍♀️👏")]
     BangEqual,
-    #[token("📈")]
     Greater,
-    #[token("📈👏")]
     GreaterEqual,
-    #[token("📉")]
     Less,
-    #[token("📉👏")]
     LessEqual,
-    #[token("🔸")]
     Comma,
+    Arrow,
     // TODO: Add "Dot"?
 
     // Literals - regexes
@@ -84,43 +71,65 @@ pub enum TokenType {
     #[regex("🗣[^\n\r]*")]
     Comment,
 
-    // Keywords - tokens
-    #[token("🤝")]
+    // Keywords
     And,
-    #[token("🤌")]
     Or,
-    #[token("👍")]
     True,
-    #[token("👎")]
     False,
-    #[token("🪄")]
     Var,
-    #[token("🔒")]
     Const,
-    #[token("🖨")]
     Print,
-    #[token("🔙")]
     Return,
-    #[token("🤔")]
     If,
-    #[token("🤷‍♀️")]
     Else,
-    #[token("🫥")]
     Nil,
-    #[token("🌀")]
     While,
-    #[token("⏳")]
     For,
-    #[token("🤖")]
     Function,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    Import,
+    Export,
 
-    // Delimiters - tokens
-    #[token("✊")]
+    // Delimiters
     EndOfExpression,
-    #[token("🔚")]
+    #[token("\n")]
+    Newline,
     EndOfFile,
 }
 
+impl TokenType {
+    /// Whether this token is spelled by the `Dialect`, rather than being a literal or comment
+    pub fn has_spelling(&self) -> bool {
+        !matches!(
+            self,
+            TokenType::Text
+                | TokenType::Number
+                | TokenType::Identifier
+                | TokenType::Comment
+                | TokenType::Newline
+        )
+    }
+
+    /// Whether a statement may end right after this token when `✊` is left out
+    pub fn can_end_statement(&self) -> bool {
+        matches!(
+            self,
+            TokenType::Identifier
+                | TokenType::Number
+                | TokenType::Text
+                | TokenType::True
+                | TokenType::False
+                | TokenType::Nil
+                | TokenType::RightParen
+                | TokenType::RightCurlyBrace
+                | TokenType::Return
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Value {
     Text(String),
@@ -128,6 +137,8 @@ pub enum Value {
     Boolean(bool),
     Nil,
     Function(Rc<EmojiFunction>),
+    NativeFunction(Rc<NativeFunction>),
+    Error(Rc<RuntimeError>),
 }
 
 impl Display for Value {
@@ -138,6 +149,8 @@ impl Display for Value {
             Self::Boolean(b) => write!(f, "{}", b),
             Self::Nil => write!(f, "nil"),
             Self::Function(func) => write!(f, "{}", func),
+            Self::NativeFunction(func) => write!(f, "{}", func),
+            Self::Error(error) => write!(f, "{}", error),
         }
     }
 }
@@ -0,0 +1,17 @@
+mod common;
+
+use common::translate;
+
+#[test]
+fn slash_comments_in_every_language() {
+    assert_eq!(translate("var a = 1; // one\n", &[]), "🪄 a 👏 1✊ 🗣 one\n");
+    assert_eq!(translate("let a = 1; // one\n", &["--from", "js"]), "🪄 a 👏 1✊ 🗣 one\n");
+}
+
+#[test]
+fn hash_comments_only_in_python() {
+    assert_eq!(translate("a = 1  # one\n", &["--from", "python"]), "🪄 a 👏 1✊  🗣 one\n");
+    // Elsewhere `#` isn't a comment, so it is copied over for `--check` to report
+    assert_eq!(translate("var a = 1; # one\n", &[]), "🪄 a 👏 1✊ # one\n");
+    assert_eq!(translate("let a = 1; # one\n", &["--from", "js"]), "🪄 a 👏 1✊ # one\n");
+}
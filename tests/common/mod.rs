@@ -0,0 +1,65 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Runs the `emoji-lang` binary with `args`
+pub fn emoji_lang(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_emoji-lang"))
+        .args(args)
+        .output()
+        .expect("emoji-lang runs")
+}
+
+/// Writes `code` to a file named `name` in a directory of its own, so tests running at the
+/// same time don't share files, and returns its path
+pub fn write_code(name: &str, code: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("emoji_lang_test_{}_{}", std::process::id(), name));
+    fs::create_dir_all(&dir).expect("temp dir is writable");
+    let path = dir.join(name);
+    fs::write(&path, code).expect("temp dir is writable");
+    path
+}
+
+/// Runs `code` with `args` after `-f`
+pub fn run_code(name: &str, code: &str, args: &[&str]) -> Output {
+    let path = write_code(name, code);
+    let path = path.to_str().expect("temp path is UTF-8");
+    emoji_lang(&[&["-f", path], args].concat())
+}
+
+/// Pipes `input` through `emoji-translator` with `args`, and returns what it printed
+pub fn translate(input: &str, args: &[&str]) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_emoji-translator"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("emoji-translator runs");
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    stdin.write_all(input.as_bytes()).expect("emoji-translator reads stdin");
+    drop(stdin);
+    let output = child.wait_with_output().expect("emoji-translator finishes");
+    assert!(output.status.success(), "emoji-translator {:?} failed", args);
+    String::from_utf8(output.stdout).expect("output is UTF-8")
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// The `(code, line)` of each error printed with `--error-format json`
+pub fn errors(output: &Output) -> Vec<(String, u64)> {
+    stderr(output)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .map(|error| {
+            let code = error["code"].as_str().unwrap_or_default().to_string();
+            (code, error["span"]["line"].as_u64().unwrap_or_default())
+        })
+        .collect()
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::{emoji_lang, run_code, stderr};
+
+#[test]
+fn bad_usage_exits_64() {
+    let output = emoji_lang(&["--no-such-flag"]);
+    assert_eq!(output.status.code(), Some(64));
+    let output = emoji_lang(&["--explain", "E999"]);
+    assert_eq!(output.status.code(), Some(64));
+}
+
+#[test]
+fn compile_error_exits_65() {
+    let output = run_code("compile_error.emoji", "🖨 1 🥂✊\n", &[]);
+    assert_eq!(output.status.code(), Some(65));
+    assert!(stderr(&output).contains("error[E"));
+}
+
+#[test]
+fn unreadable_input_exits_66() {
+    let output = emoji_lang(&["-f", "test/no_such_file.emoji"]);
+    assert_eq!(output.status.code(), Some(66));
+}
+
+#[test]
+fn runtime_error_exits_70() {
+    let output = run_code("runtime_error.emoji", "🖨 nowhere✊\n", &[]);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stderr(&output).contains("error[E"));
+}
+
+#[test]
+fn success_exits_0() {
+    let output = run_code("success.emoji", "🖨 1 🥂 1✊\n", &[]);
+    assert_eq!(output.status.code(), Some(0));
+}
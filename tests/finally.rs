@@ -0,0 +1,19 @@
+mod common;
+
+use common::{emoji_lang, stderr, stdout};
+
+#[test]
+fn finally_runs_on_return_rethrow_and_no_error() {
+    let output = emoji_lang(&["-f", "test/10.emoji"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let expected = "\
+finally after return
+returned
+rethrowing
+finally after rethrow
+caught again: oops
+no error
+finally after no error
+";
+    assert_eq!(stdout(&output), expected);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{run_code, stderr};
+
+// Runs `code` inside a `🤞` with `args`, and checks that it stops with `code` even so
+fn assert_uncatchable(name: &str, code: &str, args: &[&str], error: &str) {
+    let code = format!("🤞 🫸\n{}🫷🥅🫱e🫲🫸\n  🖨 🧵caught🧵✊\n🫷\n", code);
+    let output = run_code(name, &code, args);
+    assert_eq!(output.status.code(), Some(70), "{}", stderr(&output));
+    assert!(stderr(&output).contains(error), "{}", stderr(&output));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("caught"));
+}
+
+#[test]
+fn max_steps() {
+    let code = "  🌀🫱👍🫲🫸🫷\n";
+    assert_uncatchable("max_steps.emoji", code, &["--max-steps", "1000"], "E062");
+}
+
+// Only text is built here, doubling each time, with no loop iterations or calls to count as steps
+#[test]
+fn timeout() {
+    let code = format!("  🪄 t👏 🧵x🧵✊\n{}", "  t👏 t 🪡 t✊\n".repeat(30));
+    assert_uncatchable("timeout.emoji", &code, &["--timeout", "0.01"], "E063");
+}
+
+#[test]
+fn max_string_len() {
+    let code = "  🪄 t👏 🧵0123456789🧵✊\n  t👏 t 🪡 t✊\n  t👏 t 🪡 t✊\n";
+    assert_uncatchable("max_string_len.emoji", code, &["--max-string-len", "30"], "E064");
+}
+
+// A literal counts against the limit each time it runs, even when nothing is concatenated
+#[test]
+fn max_alloc() {
+    let code = "  🌀🫱👍🫲🫸 🪄 t👏 🧵0123456789🧵✊ 🫷\n";
+    assert_uncatchable("max_alloc.emoji", code, &["--max-alloc", "1000"], "E065");
+}
+
+#[test]
+fn max_output() {
+    let code = "  🌀🫱👍🫲🫸 🖨 🧵0123456789🧵✊ 🫷\n";
+    assert_uncatchable("max_output.emoji", code, &["--max-output", "1000"], "E066");
+}
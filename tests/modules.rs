@@ -0,0 +1,28 @@
+mod common;
+
+use common::{emoji_lang, stderr, stdout};
+
+#[test]
+fn import_cycle_names_both_files() {
+    let output = emoji_lang(&["-f", "test/9.emoji"]);
+    assert_eq!(output.status.code(), Some(70));
+    let stderr = stderr(&output);
+    let cycle = stderr.lines().find(|line| line.contains("error[E059]")).expect("an import cycle error");
+    assert!(cycle.contains("cycle_a.emoji ➡️ ") && cycle.contains("cycle_b.emoji ➡️ "), "{}", cycle);
+    assert!(cycle.ends_with("cycle_a.emoji"), "{}", cycle);
+}
+
+// Running one file of the cycle finds the cycle back to it, too
+#[test]
+fn import_cycle_back_to_entry_file() {
+    let output = emoji_lang(&["-f", "test/lib/cycle_a.emoji"]);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stderr(&output).contains("error[E059]"), "{}", stderr(&output));
+}
+
+#[test]
+fn module_imported_twice_runs_once() {
+    let output = emoji_lang(&["-f", "test/8.emoji"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "loading counter\n6\n4\n");
+}
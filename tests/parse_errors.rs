@@ -0,0 +1,47 @@
+mod common;
+
+use common::{errors, run_code};
+
+// Each broken statement is reported once, and the parser carries on at the next one
+#[test]
+fn reports_each_syntax_error_once() {
+    let code = "\
+🪄 a👏 ✊
+🖨 🫱1 🥂 ✊
+🤖 f🫱n🫲 🫸
+  🖨 n 🥂✊
+🫷
+🪄 ok👏 1✊
+";
+    let output = run_code("independent.emoji", code, &["--error-format", "json"]);
+    assert_eq!(output.status.code(), Some(65));
+    let expected = [("E039", 1), ("E039", 2), ("E039", 4)];
+    let expected: Vec<_> = expected.iter().map(|(code, line)| (code.to_string(), *line)).collect();
+    assert_eq!(errors(&output), expected);
+}
+
+// A `🫸` left open is reported where it runs out, without knock-on errors for the code inside
+#[test]
+fn unclosed_block_does_not_cascade() {
+    let code = "\
+🪄 ok👏 1✊
+🤔🫱ok🫲 🫸
+  🖨 ok✊
+  🌀🫱ok📉0🫲🫸 🖨 ok✊ 🫷
+";
+    let output = run_code("unclosed.emoji", code, &["--error-format", "json"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(errors(&output), vec![("E025".to_string(), 5)]);
+}
+
+// Past 25 errors, the parser stops with one last error saying so
+#[test]
+fn stops_at_the_error_cap() {
+    let code = "🪄 a👏 ✊\n".repeat(40);
+    let output = run_code("many.emoji", &code, &["--error-format", "json"]);
+    assert_eq!(output.status.code(), Some(65));
+    let codes: Vec<String> = errors(&output).into_iter().map(|(code, _)| code).collect();
+    assert_eq!(codes.len(), 26);
+    assert!(codes[..25].iter().all(|code| code == "E039"));
+    assert_eq!(codes[25], "E041");
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{run_code, stderr, stdout};
+use std::fs;
+use std::path::PathBuf;
+
+// A directory holding `allowed/inside.txt`, `secret.txt` next to `allowed`, and
+// `allowed/link.txt` pointing at `secret.txt`
+fn files(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("emoji_lang_permissions_{}_{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("allowed")).expect("temp dir is writable");
+    fs::write(dir.join("allowed/inside.txt"), "inside").expect("temp dir is writable");
+    fs::write(dir.join("secret.txt"), "secret").expect("temp dir is writable");
+    std::os::unix::fs::symlink(dir.join("secret.txt"), dir.join("allowed/link.txt")).expect("symlinks work");
+    dir
+}
+
+// Runs `🖨 readFile🫱🧵path🧵🫲✊` with `--fs` and `args`
+fn read_file(name: &str, path: &str, args: &[&str]) -> std::process::Output {
+    let code = format!("🖨 readFile🫱🧵{}🧵🫲✊\n", path);
+    run_code(name, &code, &[&["--fs"], args].concat())
+}
+
+fn assert_denied(output: &std::process::Output, path: &str) {
+    assert_eq!(output.status.code(), Some(70));
+    let expected = format!("error[E067]: Permission denied: read access to '{}'", path);
+    assert!(stderr(output).contains(&expected), "{}", stderr(output));
+    assert!(stderr(output).contains("run with --allow-read to allow it"), "{}", stderr(output));
+}
+
+#[test]
+fn reading_is_denied_by_default() {
+    let dir = files("default");
+    let path = dir.join("allowed/inside.txt").display().to_string();
+    let output = read_file("default.emoji", &path, &[]);
+    assert_denied(&output, &path);
+}
+
+#[test]
+fn allowed_directory_can_be_read() {
+    let dir = files("allowed");
+    let path = dir.join("allowed/inside.txt").display().to_string();
+    let flag = format!("--allow-read={}", dir.join("allowed").display());
+    let output = read_file("allowed.emoji", &path, &[&flag]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "inside\n");
+}
+
+#[test]
+fn parent_directory_escape_is_denied() {
+    let dir = files("escape");
+    let path = dir.join("allowed/../secret.txt").display().to_string();
+    let flag = format!("--allow-read={}", dir.join("allowed").display());
+    let output = read_file("escape.emoji", &path, &[&flag]);
+    assert_denied(&output, &path);
+}
+
+#[test]
+fn symlink_out_of_allowed_directory_is_denied() {
+    let dir = files("symlink");
+    let path = dir.join("allowed/link.txt").display().to_string();
+    let flag = format!("--allow-read={}", dir.join("allowed").display());
+    let output = read_file("symlink.emoji", &path, &[&flag]);
+    assert_denied(&output, &path);
+}
@@ -0,0 +1,18 @@
+mod common;
+
+use common::translate;
+
+// Blank lines after a block stay after its `🫷`, rather than opening a gap before it
+#[test]
+fn blank_lines_follow_the_block_closer() {
+    let python = "def f():\n    x = 1\n\n    return x\n\n\nprint(f())\nif True:\n    print(2)\n\n";
+    let emoji = "🤖 f🫱🫲🫸\n    🪄 x 👏 1✊\n\n    🔙 x✊\n🫷\n\n\n🖨🫱f🫱🫲🫲✊\n🤔🫱 👍🫲🫸\n    🖨🫱2🫲✊\n🫷\n\n";
+    assert_eq!(translate(python, &["--from", "python"]), emoji);
+}
+
+#[test]
+fn nested_blocks_close_before_blank_lines() {
+    let python = "while True:\n    if True:\n        print(1)\n\nprint(2)\n";
+    let emoji = "🌀🫱 👍🫲🫸\n    🤔🫱 👍🫲🫸\n        🖨🫱1🫲✊\n    🫷\n🫷\n\n🖨🫱2🫲✊\n";
+    assert_eq!(translate(python, &["--from", "python"]), emoji);
+}
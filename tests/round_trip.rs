@@ -0,0 +1,33 @@
+mod common;
+
+use common::translate;
+use std::fs;
+
+// Translating text code to emoji and back gives the same text, layout and comments included
+fn assert_round_trips(path: &str, lang: &str) {
+    let text = fs::read_to_string(path).expect("fixture exists");
+    let emoji = translate(&text, &["--lang", lang]);
+    assert_eq!(translate(&emoji, &["--lang", lang, "--reverse"]), text, "{} changed", path);
+}
+
+#[test]
+fn english_round_trips() {
+    assert_round_trips("test/english_1.txt", "en");
+    assert_round_trips("test/english_2.txt", "en");
+}
+
+#[test]
+fn other_languages_round_trip() {
+    assert_round_trips("test/es_1.txt", "es");
+    assert_round_trips("test/fr_1.txt", "fr");
+    assert_round_trips("test/pt_1.txt", "pt");
+}
+
+// Quotes and backslashes in emoji texts are escaped in text code, and read back unescaped
+#[test]
+fn quotes_and_backslashes_round_trip() {
+    let emoji = "🖨 🧵say \"hi\" \\ C:\\dir\\ \\n \\\\🧵✊\n";
+    let text = translate(emoji, &["--reverse"]);
+    assert_eq!(text, "print \"say \\\"hi\\\" \\ C:\\dir\\ \\n \\\\\\\\\";\n");
+    assert_eq!(translate(&text, &[]), emoji);
+}
@@ -0,0 +1,24 @@
+mod common;
+
+use common::{run_code, stderr, stdout};
+
+const DEEP: &str = "🤖deep🫱n🫲 🫸\n  🤔🫱n 👏👏 0🫲 🔙 0✊\n  🔙 1 🥂 deep🫱n 💔 1🫲✊\n🫷\n";
+
+// A `--max-depth` too deep for the stack raises the error before the stack runs out
+#[test]
+fn deep_recursion_stops_before_the_stack_runs_out() {
+    let code = format!("{}🖨 deep🫱1000000🫲✊\n", DEEP);
+    let output = run_code("too_deep.emoji", &code, &["--max-depth", "1000000"]);
+    assert_eq!(output.status.code(), Some(70), "{}", stderr(&output));
+    assert!(stderr(&output).contains("error[E061]"), "{}", stderr(&output));
+    assert!(stderr(&output).contains("--stack-size"), "{}", stderr(&output));
+}
+
+// Like any other stack overflow, it can be caught, leaving enough stack to handle it
+#[test]
+fn running_out_of_stack_can_be_caught() {
+    let code = format!("{}🤞 🫸\n  🖨 deep🫱1000000🫲✊\n🫷🥅🫱e🫲🫸\n  🖨 🧵caught🧵✊\n🫷\n", DEEP);
+    let output = run_code("caught_too_deep.emoji", &code, &["--max-depth", "1000000", "--stack-size", "8"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "caught\n");
+}
@@ -0,0 +1,13 @@
+use emoji_lang::suggest::closest;
+
+#[test]
+fn suggests_close_names() {
+    assert_eq!(closest("cout", ["count", "total"]), Some("count"));
+    assert_eq!(closest("totl", ["count", "total"]), Some("total"));
+}
+
+#[test]
+fn short_names_get_no_suggestions() {
+    assert_eq!(closest("x", ["c", "y"]), None);
+    assert_eq!(closest("ab", ["ac", "b"]), None);
+}
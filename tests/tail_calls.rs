@@ -0,0 +1,21 @@
+mod common;
+
+use common::{emoji_lang, run_code, stderr, stdout};
+
+// `test/7.emoji` recurses far deeper than `--max-depth 100` allows, on a 4 MiB stack, which
+// only works if each tail call replaces the call making it
+#[test]
+fn tail_recursion_runs_in_constant_stack() {
+    let output = emoji_lang(&["-f", "test/7.emoji", "--stack-size", "4", "--max-depth", "100"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "liftoff\ntrue\n");
+}
+
+// The same countdown, but with work left after the call, still nests and overflows
+#[test]
+fn other_recursion_still_hits_max_depth() {
+    let code = "🤖count🫱n🫲 🫸\n  🤔🫱n 👏👏 0🫲 🔙 0✊\n  🔙 1 🥂 count🫱n 💔 1🫲✊\n🫷\n🖨 count🫱1000🫲✊\n";
+    let output = run_code("not_a_tail_call.emoji", code, &["--stack-size", "4", "--max-depth", "100"]);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stderr(&output).contains("E061"));
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use common::{emoji_lang, stderr, stdout};
+
+// Line breaks end statements by default, which must not change what files written with a
+// `✊` after every statement do
+#[test]
+fn explicit_files_run_the_same_with_newline_terminators() {
+    for file in ["test/1.emoji", "test/2.emoji", "test/3.emoji"] {
+        let newlines = emoji_lang(&["-f", file]);
+        let explicit = emoji_lang(&["-f", file, "--explicit-terminators"]);
+        assert!(explicit.status.success(), "{}: {}", file, stderr(&explicit));
+        assert_eq!(newlines.status.code(), explicit.status.code(), "{}", file);
+        assert_eq!(stdout(&newlines), stdout(&explicit), "{}", file);
+        assert_eq!(stderr(&newlines), stderr(&explicit), "{}", file);
+    }
+}
+
+#[test]
+fn line_break_ends_a_statement() {
+    let code = "🪄 a👏 1\n🖨 a 🥂\n  1\n";
+    let output = common::run_code("newlines.emoji", code, &[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "2\n");
+    let output = common::run_code("newlines.emoji", code, &["--explicit-terminators"]);
+    assert_eq!(output.status.code(), Some(65));
+}
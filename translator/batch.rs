@@ -0,0 +1,84 @@
+use crate::{write_output, Job};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What happened to the files in a translated directory
+#[derive(Default)]
+pub struct Summary {
+    pub translated: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let icon = if self.failed > 0 { "❌" } else { "✅" };
+        write!(
+            f,
+            "{} {} translated, {} skipped, {} failed",
+            icon, self.translated, self.skipped, self.failed
+        )
+    }
+}
+
+/// Translates every matching file under `input`, mirroring the tree into `output`.
+/// Files with other extensions are skipped. With no `output`, nothing is written.
+pub fn translate_dir(input: &Path, output: Option<&Path>, job: &Job) -> Summary {
+    let (from_extension, to_extension) = job.extensions();
+    let mut summary = Summary::default();
+    let mut files = Vec::new();
+    if let Err(e) = collect_files(input, &mut files) {
+        eprintln!("❌ Failed to read directory {}: {}", input.display(), e);
+        summary.failed += 1;
+    }
+    for path in files {
+        if path.extension().is_none_or(|extension| extension != from_extension) {
+            summary.skipped += 1;
+            continue;
+        }
+        let relative = path.strip_prefix(input).expect("files are collected under input");
+        let target = output.map(|output| output.join(relative).with_extension(to_extension));
+        match translate_file(&path, target.as_deref(), job) {
+            Ok(()) => summary.translated += 1,
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("{}", error);
+                }
+                eprintln!("❌ Failed to translate {}", path.display());
+                summary.failed += 1;
+            }
+        }
+    }
+    summary
+}
+
+fn translate_file(path: &Path, target: Option<&Path>, job: &Job) -> Result<(), Vec<String>> {
+    let input = fs::read_to_string(path).map_err(|e| vec![e.to_string()])?;
+    let output = job.translate(&path.display().to_string(), &input)?;
+    let Some(target) = target else {
+        return Ok(());
+    };
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| vec![e.to_string()])?;
+    }
+    write_output(target, &output).map_err(|e| vec![e.to_string()])?;
+    println!("✅ {} ➡️ {}", path.display(), target.display());
+    Ok(())
+}
+
+/// Every file under `dir`, in a stable order
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
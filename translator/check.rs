@@ -0,0 +1,23 @@
+use crate::forward::Translation;
+use emoji_lang::dialect::Dialect;
+use emoji_lang::error::capture_errors;
+use emoji_lang::lexer::{scan_tokens, LexerOptions};
+use emoji_lang::parser::Parser;
+use emoji_lang::source::add_mapped_file;
+use std::sync::Arc;
+
+/// Lexes and parses the translated code the same way `emoji-lang` would, and returns
+/// any errors with their positions mapped back to the text input.
+pub fn check(translation: &Translation, dialect: Dialect) -> Vec<String> {
+    let name = format!("{} (translated)", translation.source_map.source_name);
+    let base = add_mapped_file(&name, &translation.code, translation.source_map.clone());
+    let (_, errors) = capture_errors(|| {
+        let options = LexerOptions {
+            dialect: Arc::new(dialect),
+            ..LexerOptions::default()
+        };
+        let scanned = scan_tokens(&translation.code, base, &options);
+        Parser::new(scanned.tokens).parse()
+    });
+    errors.iter().map(|error| error.format()).collect()
+}
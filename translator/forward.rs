@@ -0,0 +1,219 @@
+use crate::tokenizer::{tokenize, Lexeme, LexemeKind};
+use emoji_lang::diagnostic::Diagnostic;
+use emoji_lang::dialect::Dialect;
+use emoji_lang::message::Message;
+use emoji_lang::source;
+use emoji_lang::source_map::{Mapping, SourceMap};
+use emoji_lang::token::TokenType;
+use logos::Span;
+
+pub struct Translation {
+    pub code: String,
+    pub source_map: SourceMap,
+    // Input the front end couldn't translate, reported against the input file
+    pub errors: Vec<Diagnostic>,
+}
+
+/// Builds translated code, remembering where each translated token came from
+pub struct Emitter {
+    code: String,
+    mappings: Vec<Mapping>,
+    errors: Vec<(Span, Message)>,
+}
+
+impl Emitter {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            code: String::with_capacity(capacity),
+            mappings: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Where the next text will be written
+    pub fn position(&self) -> usize {
+        self.code.len()
+    }
+
+    /// Writes code the front end made up at an earlier `position`, moving what follows
+    pub fn insert(&mut self, position: usize, text: &str) {
+        self.code.insert_str(position, text);
+        for mapping in &mut self.mappings {
+            if mapping.target >= position {
+                mapping.target += text.len();
+            }
+        }
+    }
+
+    /// Reports input at `span` that can't be translated
+    pub fn error(&mut self, span: Span, message: impl Into<Message>) {
+        self.errors.push((span, message.into()));
+    }
+
+    /// Writes code that was translated from the input at `source`
+    pub fn token(&mut self, source: usize, text: &str) {
+        self.mappings.push(Mapping {
+            source,
+            target: self.code.len(),
+        });
+        self.code.push_str(text);
+    }
+
+    /// Writes layout, or code the front end made up, without a mapping of its own
+    pub fn push(&mut self, text: &str) {
+        self.code.push_str(text);
+    }
+
+    /// Where the blank lines at the end of the code so far start, or the end of the code
+    /// if there are none
+    pub fn blank_lines_start(&self) -> usize {
+        let content = self.code.trim_end();
+        match self.code[content.len()..].find('\n') {
+            Some(newline) => content.len() + newline + 1,
+            None => self.code.len(),
+        }
+    }
+
+    pub fn ends_with(&self, text: &str) -> bool {
+        self.code.ends_with(text)
+    }
+
+    /// Writes one input lexeme in its emoji-lang spelling
+    pub fn lexeme(&mut self, lexeme: &Lexeme, dialect: &Dialect) {
+        let text = lexeme.text;
+        match lexeme.kind {
+            LexemeKind::Whitespace => self.push(text),
+            LexemeKind::Spelled(token_type) => {
+                self.token(lexeme.offset, dialect.emoji_for(token_type).unwrap_or(text))
+            }
+            LexemeKind::Text => self.token(lexeme.offset, &format!("🧵{}🧵", unescape(text))),
+            LexemeKind::Comment => {
+                let comment = text.strip_prefix("//").or(text.strip_prefix('#'));
+                self.token(lexeme.offset, &format!("🗣{}", comment.unwrap_or(text)))
+            }
+            // Identifiers, numbers and anything unrecognized are copied over as they are
+            _ => self.token(lexeme.offset, text),
+        }
+    }
+
+    pub fn finish(mut self, input_path: &str, input: &str) -> Translation {
+        // Errors "at end" of the output belong at the end of the input too
+        self.token(input.len(), "");
+        let errors = if self.errors.is_empty() {
+            Vec::new()
+        } else {
+            let base = source::add_file(input_path, input);
+            let at = |span: Span| base + span.start..base + span.end;
+            self.errors.into_iter().map(|(span, message)| Diagnostic::error(at(span), message)).collect()
+        };
+        Translation {
+            code: self.code,
+            source_map: SourceMap {
+                source_name: input_path.to_string(),
+                source_text: input.to_string(),
+                mappings: self.mappings,
+            },
+            errors,
+        }
+    }
+}
+
+// The contents of a quoted text, with `\\` and an escaped quote read as the character they
+// stand for. Other escapes, like `\n`, are kept as written, since emoji-lang has none.
+fn unescape(text: &str) -> String {
+    let quote = text.chars().next().unwrap_or('"');
+    let mut contents = String::with_capacity(text.len());
+    let mut chars = text[1..text.len() - 1].chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next)) if next == '\\' || next == quote => {
+                contents.push(next);
+                chars.next();
+            }
+            _ => contents.push(c),
+        }
+    }
+    contents
+}
+
+/// Translates brace-and-semicolon languages (Lox, JavaScript) token by token
+pub fn translate_to_emoji_lang(input_path: &str, input: &str, dialect: &Dialect) -> Translation {
+    let lexemes = tokenize(input, dialect, false);
+    let arrows = arrow_functions(&lexemes);
+    let mut emitter = Emitter::new(input.len() * 2);
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        match arrows.iter().find(|arrow| arrow.params == i) {
+            Some(arrow) if arrow.bare_param => emitter.push("🤖🫱"),
+            Some(_) => emitter.push("🤖"),
+            None => {}
+        }
+        let arrow = arrows.iter().find(|arrow| arrow.arrow == i);
+        if !arrow.is_some_and(|arrow| arrow.block_body) {
+            emitter.lexeme(lexeme, dialect);
+        }
+        if arrows.iter().any(|arrow| arrow.bare_param && arrow.params == i) {
+            emitter.push("🫲");
+        }
+    }
+    emitter.finish(input_path, input)
+}
+
+/// A JavaScript arrow function, `(a, b) => a + b` or `n => n * n`, which emoji-lang writes
+/// as a `🤖` lambda: `🤖🫱a🔸 b🫲👉 a 🥂 b`
+struct ArrowFunction {
+    // Index of the `(` its parameters start at, or of its one unbracketed parameter
+    params: usize,
+    bare_param: bool,
+    // Index of the `=>`
+    arrow: usize,
+    // A `{` body follows, so the `=>` is dropped: `🤖🫱a🫲🫸 ... 🫷`
+    block_body: bool,
+}
+
+fn arrow_functions(lexemes: &[Lexeme]) -> Vec<ArrowFunction> {
+    let is_code = |l: &Lexeme| !matches!(l.kind, LexemeKind::Whitespace | LexemeKind::Comment);
+    let code: Vec<usize> = (0..lexemes.len()).filter(|&i| is_code(&lexemes[i])).collect();
+    let spelled = |i: usize, token_type| lexemes[i].kind == LexemeKind::Spelled(token_type);
+    let mut arrows = Vec::new();
+    for (n, &arrow) in code.iter().enumerate() {
+        if !spelled(arrow, TokenType::Arrow) || n == 0 {
+            continue;
+        }
+        let block_body = code.get(n + 1).is_some_and(|&next| spelled(next, TokenType::LeftCurlyBrace));
+        let before = code[n - 1];
+        if lexemes[before].kind == LexemeKind::Word {
+            arrows.push(ArrowFunction {
+                params: before,
+                bare_param: true,
+                arrow,
+                block_body,
+            });
+            continue;
+        }
+        if !spelled(before, TokenType::RightParen) {
+            continue;
+        }
+        // Find the `(` matching the `)` before the arrow
+        let mut depth = 0;
+        let open = code[..n].iter().rposition(|&i| {
+            if spelled(i, TokenType::RightParen) {
+                depth += 1;
+            } else if spelled(i, TokenType::LeftParen) {
+                depth -= 1;
+            }
+            depth == 0
+        });
+        // `fun (x) => x` already says it is a function
+        let Some(open) = open else { continue };
+        if open > 0 && spelled(code[open - 1], TokenType::Function) {
+            continue;
+        }
+        arrows.push(ArrowFunction {
+            params: code[open],
+            bare_param: false,
+            arrow,
+            block_body,
+        });
+    }
+    arrows
+}
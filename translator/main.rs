@@ -1,88 +1,249 @@
-use clap::Parser;
-use std::collections::BTreeMap;
+mod check;
+mod batch;
+mod forward;
+mod python;
+mod reverse;
+mod tokenizer;
+
+use crate::batch::translate_dir;
+use crate::check::check;
+use crate::forward::{translate_to_emoji_lang, Translation};
+use crate::python::translate_python;
+use crate::reverse::translate_from_emoji_lang;
+use clap::{Parser, ValueEnum};
+use emoji_lang::diagnostic::{set_error_format, ErrorFormat};
+use emoji_lang::dialect::Dialect;
+use emoji_lang::exit_code;
+use emoji_lang::message::{init_locale, Locale};
+use emoji_lang::source_map::SourceMap;
+use std::fmt::Display;
 use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::exit;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Args {
-    #[arg(short, long)]
+    /// File or directory to translate, or `-` for stdin
+    #[arg(short, long, default_value = "-")]
     input: String,
+    /// File or directory to write to, or `-` for stdout
     #[arg(short, long)]
     output: Option<String>,
     #[arg(long)]
     dry_run: bool,
+    /// Translate emoji-lang code back into readable text code
+    #[arg(long)]
+    reverse: bool,
+    /// Parse the translated code and report errors against the input file
+    #[arg(long, conflicts_with = "reverse")]
+    check: bool,
+    /// Also write a source map to `<output>.map`, for `emoji-lang --source-map`
+    #[arg(long, conflicts_with = "reverse", requires = "output")]
+    source_map: bool,
+    /// Language the input is written in
+    #[arg(long, value_enum, default_value_t = Language::Lox)]
+    from: Language,
+    /// Keyword language of the text code, for both directions
+    #[arg(long, value_enum, default_value_t = Lang::En)]
+    lang: Lang,
+    /// Language of `--check` error messages (see `emoji-lang --locale`)
+    #[arg(long)]
+    locale: Option<Locale>,
+    /// How `--check` errors are printed (see `emoji-lang --error-format`)
+    #[arg(long, default_value = "human")]
+    error_format: ErrorFormat,
+    /// TOML file respelling keywords and operators (see `dialects/default.toml`)
+    #[arg(long)]
+    dialect: Option<String>,
 }
 
-fn main() {
-    let args = Args::parse();
-    let input_code = fs::read_to_string(&args.input)
-        .unwrap_or_else(|_| panic!("❌ Failed to read input file: {}", args.input));
-    let output_code = translate_to_emoji_lang(&input_code);
-    if args.dry_run {
-        println!("{}", output_code);
-    } else if let Some(output_file_path) = args.output {
-        fs::write(&output_file_path, output_code)
-            .unwrap_or_else(|_| panic!("Failed to write to output file: {}", output_file_path));
-        println!("✅ Wrote translated emoji code to {}", output_file_path);
-    } else {
-        eprintln!("❌ Provide an output file path with --output, or use --dry-run");
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Language {
+    Lox,
+    Js,
+    Python,
+}
+
+impl Language {
+    /// Spellings this language adds on top of the default dialect
+    fn overrides(self) -> Option<&'static str> {
+        match self {
+            Language::Lox => None,
+            Language::Js => Some(include_str!("../dialects/js.toml")),
+            Language::Python => Some(include_str!("../dialects/python.toml")),
+        }
     }
+
+    fn translate(self, input_path: &str, input: &str, dialect: &Dialect) -> Translation {
+        match self {
+            Language::Lox | Language::Js => translate_to_emoji_lang(input_path, input, dialect),
+            Language::Python => translate_python(input_path, input, dialect),
+        }
+    }
+}
+
+/// Human language the keywords of text code are written in
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Lang {
+    En,
+    Es,
+    Fr,
+    Pt,
 }
 
-fn translate_to_emoji_lang(input: &str) -> String {
-    let mut map = BTreeMap::new();
-
-    // Longer patterns first to avoid premature partial matches
-    map.insert("==", "👏👏");
-    map.insert("!=", "🙅‍♀️👏");
-    map.insert(">=", "📈👏");
-    map.insert("<=", "📉👏");
-    // map.insert(">=", "🛫");
-    // map.insert("<=", "🛬");
-    map.insert("+", "🥂");
-    map.insert("-", "💔");
-    map.insert("*", "✨");
-    map.insert("/", "🔪");
-    map.insert("%", "⚡️");
-    map.insert("=", "👏");
-    map.insert(">", "📈");
-    map.insert("<", "📉");
-    map.insert(",", "🔸");
-
-    // Keywords
-    map.insert("function", "🤖");
-    map.insert("fn", "🤖");
-    map.insert("fun", "🤖");
-    map.insert("var", "🪄");
-    map.insert("const", "🔒");
-    map.insert("if", "🤔");
-    map.insert("else", "🤷‍♀️");
-    map.insert("true", "👍");
-    map.insert("false", "👎");
-    map.insert("nil", "🫥");
-    map.insert("return", "🔙");
-    map.insert("while", "🌀");
-    map.insert("for", "⏳");
-    map.insert("print", "🖨");
-    map.insert("and", "🤝");
-    map.insert("or", "🤌");
-
-    // Brackets & delimiters
-    map.insert("(", "🫱");
-    map.insert(")", "🫲");
-    map.insert("{", "🫸");
-    map.insert("}", "🫷");
-    map.insert(";", "✊");
-
-    // Process replacements in order
-    let mut result = input.to_string();
-    for (k, v) in &map {
-        result = result.replace(k, v);
+impl Lang {
+    fn overrides(self) -> Option<&'static str> {
+        match self {
+            Lang::En => None,
+            Lang::Es => Some(include_str!("../dialects/es.toml")),
+            Lang::Fr => Some(include_str!("../dialects/fr.toml")),
+            Lang::Pt => Some(include_str!("../dialects/pt.toml")),
+        }
     }
+}
+
+/// Everything needed to translate one file, shared by single-file and directory mode
+pub struct Job {
+    from: Language,
+    reverse: bool,
+    check: bool,
+    source_map: bool,
+    dialect: Dialect,
+}
+
+/// A translated file, and its source map if one was asked for
+pub struct Output {
+    pub code: String,
+    pub source_map: Option<SourceMap>,
+}
+
+impl Job {
+    /// Translates `input`, or returns the errors `--check` found in the translation
+    pub fn translate(&self, input_path: &str, input: &str) -> Result<Output, Vec<String>> {
+        if self.reverse {
+            return Ok(Output {
+                code: translate_from_emoji_lang(input, &self.dialect),
+                source_map: None,
+            });
+        }
+        let translation = self.from.translate(input_path, input, &self.dialect);
+        if !translation.errors.is_empty() {
+            return Err(translation.errors.iter().map(|error| error.format()).collect());
+        }
+        if self.check {
+            let errors = check(&translation, self.dialect.clone());
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+        }
+        Ok(Output {
+            source_map: self.source_map.then_some(translation.source_map),
+            code: translation.code,
+        })
+    }
+
+    /// The extension of files this job reads, and of the files it writes
+    pub fn extensions(&self) -> (&'static str, &'static str) {
+        if self.reverse {
+            ("emoji", "txt")
+        } else {
+            ("txt", "emoji")
+        }
+    }
+}
+
+/// Writes `output` to `path`, with its source map next to it at `<path>.map`
+pub fn write_output(path: &Path, output: &Output) -> std::io::Result<()> {
+    fs::write(path, &output.code)?;
+    if let Some(source_map) = &output.source_map {
+        fs::write(format!("{}.map", path.display()), source_map.to_string())?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = Args::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        exit(if e.use_stderr() { exit_code::USAGE } else { 0 })
+    });
+    init_locale(args.locale);
+    set_error_format(args.error_format);
+    if args.reverse && args.from != Language::Lox {
+        fail(exit_code::USAGE, "--reverse only writes Lox-style code, so it can't be combined with --from");
+    }
+    let mut dialect = Dialect::builtin();
+    for overrides in [args.from.overrides(), args.lang.overrides()].into_iter().flatten() {
+        dialect = dialect
+            .with_overrides(overrides)
+            .expect("the built-in front end and language dialects are valid");
+    }
+    if let Some(path) = &args.dialect {
+        dialect = dialect.with_file(path).unwrap_or_else(|e| fail(exit_code::USAGE, format!("{:#}", e)));
+    }
+    let job = Job {
+        from: args.from,
+        reverse: args.reverse,
+        check: args.check,
+        source_map: args.source_map,
+        dialect,
+    };
+
+    if Path::new(&args.input).is_dir() {
+        let output_dir = match (&args.output, args.dry_run) {
+            (Some(output), false) if output != "-" => Some(Path::new(output)),
+            (_, true) => None,
+            _ => {
+                fail(exit_code::USAGE, "Translating a directory needs an output directory (--output), or --dry-run");
+            }
+        };
+        let summary = translate_dir(Path::new(&args.input), output_dir, &job);
+        eprintln!("{}", summary);
+        if summary.failed > 0 {
+            exit(exit_code::COMPILE);
+        }
+        return;
+    }
+
+    let reads_stdin = args.input == "-";
+    let input_code = if reads_stdin {
+        let mut input = String::new();
+        io::stdin()
+            .read_to_string(&mut input)
+            .unwrap_or_else(|e| fail(exit_code::USAGE, format!("Failed to read stdin: {}", e)));
+        input
+    } else {
+        fs::read_to_string(&args.input)
+            .unwrap_or_else(|e| fail(exit_code::NO_INPUT, format!("Failed to read input file {}: {}", args.input, e)))
+    };
+    let input_name = if reads_stdin { "<stdin>" } else { args.input.as_str() };
+    let output = job.translate(input_name, &input_code).unwrap_or_else(|errors| {
+        for error in &errors {
+            eprintln!("{}", error);
+        }
+        fail(exit_code::COMPILE, format!("Translated code has {} error(s)", errors.len()));
+    });
+    match args.output.as_deref() {
+        // Piped input goes to stdout unless an output file is named
+        _ if args.dry_run => print!("{}", output.code),
+        Some("-") => print!("{}", output.code),
+        None if reads_stdin => print!("{}", output.code),
+        Some(output_file_path) => {
+            write_output(Path::new(output_file_path), &output)
+                .unwrap_or_else(|e| {
+                    fail(exit_code::USAGE, format!("Failed to write to output file {}: {}", output_file_path, e))
+                });
+            println!("✅ Wrote translated code to {}", output_file_path);
+            if output.source_map.is_some() {
+                println!("✅ Wrote source map to {}.map", output_file_path);
+            }
+        }
+        None => fail(exit_code::USAGE, "Provide an output file path with --output, or use --dry-run"),
+    }
+}
 
-    // Add an EOF token
-    result = result.trim_end().to_string();
-    result.push_str("\n🔚\n");
-    
-    result
+fn fail(code: i32, message: impl Display) -> ! {
+    eprintln!("❌ {}", message);
+    exit(code)
 }
@@ -0,0 +1,392 @@
+use crate::forward::{Emitter, Translation};
+use crate::tokenizer::{tokenize, Lexeme, LexemeKind};
+use emoji_lang::dialect::Dialect;
+use emoji_lang::message::Code;
+use emoji_lang::token::TokenType;
+use std::collections::HashSet;
+
+/// An indented block that is still open
+struct Block {
+    // Indentation of the line that opened it, like `def f():` or `if x:`
+    indent: usize,
+    indent_text: String,
+    // Indentation of the lines inside it, once the first one is seen
+    body_indent: Option<usize>,
+    is_function: bool,
+    // The counter a `for` loop declares, which only exists inside the loop
+    loop_variable: Option<String>,
+}
+
+/// The top level, or the body of a `def`
+struct Scope {
+    names: HashSet<String>,
+    // Names first assigned inside an `if`, `while` or other block. emoji-lang would scope a
+    // `🪄` there to the block, so these are declared at the start of the scope instead.
+    hoisted: Vec<String>,
+    // Where in the output the scope's code starts
+    start: usize,
+}
+
+impl Scope {
+    fn new(names: HashSet<String>, start: usize) -> Self {
+        Self {
+            names,
+            hoisted: Vec::new(),
+            start,
+        }
+    }
+}
+
+/// Translates Python-style code: indented blocks become `🫸`/`🫷`, the end of each
+/// simple statement becomes `✊`, and the first assignment to a name declares it with `🪄`.
+pub fn translate_python(input_path: &str, input: &str, dialect: &Dialect) -> Translation {
+    let mut translator = PythonTranslator {
+        out: Emitter::new(input.len() * 2),
+        dialect,
+        blocks: Vec::new(),
+        scopes: vec![Scope::new(HashSet::new(), 0)],
+        depth: 0,
+    };
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        translator.line(line, offset);
+        offset += line.len();
+    }
+    translator.close_blocks(0);
+    translator.end_scope();
+    translator.out.finish(input_path, input)
+}
+
+struct PythonTranslator<'a> {
+    out: Emitter,
+    dialect: &'a Dialect,
+    blocks: Vec<Block>,
+    // The top level, then each enclosing `def`
+    scopes: Vec<Scope>,
+    // Open brackets, for statements that continue onto the next line
+    depth: usize,
+}
+
+impl PythonTranslator<'_> {
+    fn line(&mut self, line: &str, offset: usize) {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let newline = &line[content.len()..];
+        let code = content.trim_start();
+        let indent_text = &content[..content.len() - code.len()];
+        let lexemes: Vec<Lexeme> = tokenize(code, self.dialect, true)
+            .into_iter()
+            .map(|lexeme| Lexeme {
+                offset: lexeme.offset + offset + indent_text.len(),
+                ..lexeme
+            })
+            .collect();
+        let significant: Vec<&Lexeme> = lexemes
+            .iter()
+            .filter(|l| !matches!(l.kind, LexemeKind::Whitespace | LexemeKind::Comment))
+            .collect();
+
+        // Blank lines, comments and continuation lines don't affect blocks
+        let continues = self.depth > 0;
+        if !continues && !significant.is_empty() {
+            let indent = indent_text.chars().count();
+            self.close_blocks(indent);
+            self.check_indent(indent, significant[0]);
+        }
+        self.out.push(indent_text);
+        if continues || significant.is_empty() {
+            self.body(&lexemes);
+            self.out.push(newline);
+            return;
+        }
+
+        let opens_block = significant.last().is_some_and(|l| l.text == ":");
+        let first = significant[0];
+        if opens_block {
+            self.blocks.push(Block {
+                indent: indent_text.chars().count(),
+                indent_text: indent_text.to_string(),
+                body_indent: None,
+                is_function: first.kind == LexemeKind::Spelled(TokenType::Function),
+                loop_variable: None,
+            });
+            self.header(&lexemes, &significant);
+        } else {
+            self.statement(&lexemes, &significant);
+        }
+        self.out.push(newline);
+    }
+
+    /// Closes every block opened at `indent` or deeper
+    fn close_blocks(&mut self, indent: usize) {
+        while self
+            .blocks
+            .last()
+            .is_some_and(|block| block.indent >= indent)
+        {
+            let block = self.blocks.pop().expect("checked above");
+            // The `🫷` goes right after the block's last line, before any blank lines after it
+            let at = self.out.blank_lines_start();
+            let newline = if self.out.ends_with("\n") || at < self.out.position() { "" } else { "\n" };
+            self.out.insert(at, &format!("{}{}🫷\n", newline, block.indent_text));
+            if block.is_function {
+                self.end_scope();
+            }
+        }
+    }
+
+    /// Checks a line lines up with the block it is in, after the blocks it closes. The first
+    /// line of a block sets how far the rest of it is indented.
+    fn check_indent(&mut self, indent: usize, first: &Lexeme) {
+        let Some(block) = self.blocks.last_mut() else {
+            return;
+        };
+        match block.body_indent {
+            None => block.body_indent = Some(indent),
+            Some(body_indent) if indent < body_indent => {
+                let span = first.offset..first.offset + first.text.len();
+                self.out.error(span, Code::UnmatchedDedent);
+            }
+            Some(_) => {}
+        }
+    }
+
+    /// Declares the names hoisted out of the scope's blocks at its start
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        let declarations: String = if self.scopes.is_empty() {
+            scope.hoisted.iter().map(|name| format!("🪄 {}✊\n", name)).collect()
+        } else {
+            scope.hoisted.iter().map(|name| format!(" 🪄 {}✊", name)).collect()
+        };
+        self.out.insert(scope.start, &declarations);
+    }
+
+    /// A line ending in `:`, which opens an indented block
+    fn header(&mut self, lexemes: &[Lexeme], significant: &[&Lexeme]) {
+        let first = significant[0];
+        let colon = significant[significant.len() - 1];
+        let (before_colon, after_colon) = split_at_offset(lexemes, colon.offset);
+        let mut scope = None;
+        match first.kind {
+            LexemeKind::Word if first.text == "elif" => {
+                self.out.token(first.offset, "🤷‍♀️ 🤔🫱");
+                self.condition(&before_colon[1..]);
+            }
+            LexemeKind::Spelled(TokenType::If) | LexemeKind::Spelled(TokenType::While) => {
+                self.out.lexeme(first, self.dialect);
+                self.out.push("🫱");
+                self.condition(&before_colon[1..]);
+            }
+            LexemeKind::Spelled(TokenType::For) => self.for_range(first, significant),
+            LexemeKind::Spelled(TokenType::Catch) => {
+                self.out.lexeme(first, self.dialect);
+                // `except ValueError as e:` catches everything as `e`; emoji-lang errors have no types
+                if let [.., as_, name, _] = significant
+                    && as_.text == "as"
+                {
+                    self.out.push("🫱");
+                    self.out.token(name.offset, name.text);
+                    self.out.push("🫲");
+                }
+            }
+            LexemeKind::Spelled(TokenType::Function) => {
+                let params = significant
+                    .iter()
+                    .skip_while(|l| l.kind != LexemeKind::Spelled(TokenType::LeftParen))
+                    .filter(|l| l.kind == LexemeKind::Word)
+                    .map(|l| l.text.to_string());
+                scope = Some(params.collect());
+                if let Some(name) = significant.get(1) {
+                    self.declare(name.text);
+                }
+                self.body(before_colon);
+            }
+            _ => self.body(before_colon),
+        }
+        self.out.token(colon.offset, "🫸");
+        if let Some(names) = scope {
+            self.scopes.push(Scope::new(names, self.out.position()));
+        }
+        self.body(after_colon);
+    }
+
+    fn condition(&mut self, lexemes: &[Lexeme]) {
+        let code_end = lexemes
+            .iter()
+            .rposition(|l| l.kind != LexemeKind::Whitespace)
+            .map_or(0, |i| i + 1);
+        self.body(&lexemes[..code_end]);
+        self.out.push("🫲");
+        self.body(&lexemes[code_end..]);
+    }
+
+    /// `for i in range(a, b):` becomes a counting `⏳` loop. Other loops can't be translated.
+    fn for_range(&mut self, keyword: &Lexeme, significant: &[&Lexeme]) {
+        let is_range = |l: &&Lexeme| l.text == "range";
+        self.out.lexeme(keyword, self.dialect);
+        let (Some(name), Some(in_), Some(range)) =
+            (significant.get(1), significant.get(2), significant.get(3))
+        else {
+            let span = keyword.offset..keyword.offset + keyword.text.len();
+            return self.out.error(span, Code::UnsupportedForLoop);
+        };
+        if in_.text != "in" || !is_range(range) {
+            let last = significant[significant.len() - 1];
+            return self.out.error(in_.offset..last.offset, Code::UnsupportedForLoop);
+        }
+        // Everything between `range(` and the closing `):`
+        let args = &significant[5..significant.len().saturating_sub(2).max(5)];
+        let mut bounds = split_args(args).into_iter();
+        let (start, end) = match (bounds.next(), bounds.next()) {
+            (Some(start), Some(end)) => (Some(start), end),
+            (Some(end), None) => (None, end),
+            _ => (None, &[][..]),
+        };
+        if let Some(step) = bounds.next() {
+            let span = match (step.first(), step.last()) {
+                (Some(first), Some(last)) => first.offset..last.offset + last.text.len(),
+                _ => range.offset..range.offset + range.text.len(),
+            };
+            return self.out.error(span, Code::UnsupportedRangeStep);
+        }
+        let name_text = name.text;
+        if let Some(block) = self.blocks.last_mut() {
+            block.loop_variable = Some(name_text.to_string());
+        }
+        self.out.push("🫱🪄");
+        self.out.token(name.offset, name_text);
+        self.out.push("👏");
+        match start {
+            Some(start) => start.iter().for_each(|l| self.out.lexeme(l, self.dialect)),
+            None => self.out.push("0"),
+        }
+        self.out.push(&format!("✊{}📉", name_text));
+        end.iter().for_each(|l| self.out.lexeme(l, self.dialect));
+        self.out.push(&format!("✊{0}👏{0}🥂1🫲", name_text));
+    }
+
+    /// A simple statement, ended with `✊` unless its brackets continue on the next line
+    fn statement(&mut self, lexemes: &[Lexeme], significant: &[&Lexeme]) {
+        if let [pass] = significant
+            && pass.kind == LexemeKind::Word
+            && pass.text == "pass"
+        {
+            // An empty block is emoji-lang's empty statement
+            let (_, after) = split_at_offset(lexemes, pass.offset);
+            self.out.token(pass.offset, "🫸🫷");
+            self.body(after);
+            return;
+        }
+        let mut rest = lexemes;
+        if let [name, op, equal, ..] = significant
+            && name.kind == LexemeKind::Word
+        {
+            let is_equal = |l: &Lexeme| l.kind == LexemeKind::Spelled(TokenType::Equal);
+            let is_arithmetic = matches!(
+                op.kind,
+                LexemeKind::Spelled(
+                    TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash
+                )
+            );
+            if is_arithmetic && is_equal(equal) && op.offset + op.text.len() == equal.offset {
+                // `x += 1` becomes `x 👏 x 🥂 1`
+                self.out.token(name.offset, name.text);
+                self.out.push(" ");
+                self.out.lexeme(equal, self.dialect);
+                self.out.push(" ");
+                self.out.token(name.offset, name.text);
+                self.out.push(" ");
+                self.out.lexeme(op, self.dialect);
+                let (_, after) = split_at_offset(lexemes, equal.offset);
+                rest = after;
+            } else if is_equal(op) && !self.is_declared(name.text) {
+                if self.blocks.last().is_some_and(|block| !block.is_function) {
+                    self.hoist(name.text);
+                } else {
+                    self.declare(name.text);
+                    self.out.push("🪄 ");
+                }
+            }
+        }
+        let code_end = rest
+            .iter()
+            .rposition(|l| !matches!(l.kind, LexemeKind::Whitespace | LexemeKind::Comment))
+            .map_or(0, |i| i + 1);
+        self.body(&rest[..code_end]);
+        if self.depth == 0 {
+            self.out.push("✊");
+        }
+        self.body(&rest[code_end..]);
+    }
+
+    fn body(&mut self, lexemes: &[Lexeme]) {
+        for lexeme in lexemes {
+            match lexeme.kind {
+                LexemeKind::Spelled(TokenType::LeftParen) => self.depth += 1,
+                LexemeKind::Spelled(TokenType::RightParen) => {
+                    self.depth = self.depth.saturating_sub(1)
+                }
+                _ => {}
+            }
+            self.out.lexeme(lexeme, self.dialect);
+        }
+    }
+
+    fn declare(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.names.insert(name.to_string());
+        }
+    }
+
+    fn hoist(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.names.insert(name.to_string());
+            scope.hoisted.push(name.to_string());
+        }
+    }
+
+    /// Whether `name` is declared in the current scope, or is the counter of a loop it is in
+    fn is_declared(&self, name: &str) -> bool {
+        let is_loop_variable = self
+            .blocks
+            .iter()
+            .rev()
+            .take_while(|block| !block.is_function)
+            .any(|block| block.loop_variable.as_deref() == Some(name));
+        is_loop_variable || self.scopes.last().is_some_and(|scope| scope.names.contains(name))
+    }
+}
+
+/// Splits `range(...)` arguments at the commas that aren't inside brackets
+fn split_args<'l, 'a>(args: &'l [&'l Lexeme<'a>]) -> Vec<&'l [&'l Lexeme<'a>]> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, lexeme) in args.iter().enumerate() {
+        match lexeme.kind {
+            LexemeKind::Spelled(TokenType::LeftParen) => depth += 1,
+            LexemeKind::Spelled(TokenType::RightParen) => depth = depth.saturating_sub(1),
+            LexemeKind::Spelled(TokenType::Comma) if depth == 0 => {
+                parts.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !args.is_empty() {
+        parts.push(&args[start..]);
+    }
+    parts
+}
+
+/// Splits lexemes into those before `offset` and those from it onwards, skipping the one at it
+fn split_at_offset<'l, 'a>(
+    lexemes: &'l [Lexeme<'a>],
+    offset: usize,
+) -> (&'l [Lexeme<'a>], &'l [Lexeme<'a>]) {
+    let index = lexemes.partition_point(|l| l.offset < offset);
+    let after = (index + 1).min(lexemes.len());
+    (&lexemes[..index], &lexemes[after..])
+}
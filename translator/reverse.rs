@@ -0,0 +1,57 @@
+use emoji_lang::dialect::Dialect;
+use emoji_lang::lexer::RawTokens;
+use emoji_lang::token::TokenType;
+
+/// Turns emoji-lang code back into text code. Everything between tokens (spaces,
+/// indentation, line breaks) is copied over as-is, so the layout is preserved.
+pub fn translate_from_emoji_lang(input: &str, dialect: &Dialect) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut copied = 0;
+    for (token, span) in RawTokens::new(input, dialect) {
+        let slice = &input[span.clone()];
+        let text = match token {
+            Ok(TokenType::Text) => {
+                let quote = '🧵'.len_utf8();
+                format!("\"{}\"", escape(&slice[quote..slice.len() - quote]))
+            }
+            Ok(TokenType::Comment) => format!("//{}", &slice['🗣'.len_utf8()..]),
+            // The text language has no end marker, so `🔚` and the data after it stay as they are
+            Ok(TokenType::EndOfFile) => break,
+            Ok(token_type) => dialect.text_for(token_type).unwrap_or(slice).to_string(),
+            Err(_) => slice.to_string(),
+        };
+        let gap = &input[copied..span.start];
+        // `🔙n` needs a space once it becomes `return n`
+        if gap.is_empty() && needs_space(&result, &text) {
+            result.push(' ');
+        }
+        result.push_str(gap);
+        result.push_str(&text);
+        copied = span.end;
+    }
+    result.push_str(&input[copied..]);
+    result
+}
+
+// Escapes `"`, and each `\` that would otherwise be read as part of an escape, so the
+// forward translation gives back `contents` exactly; a `\n` stays as it is either way
+fn escape(contents: &str) -> String {
+    let mut escaped = String::with_capacity(contents.len());
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' if matches!(chars.peek(), None | Some('\\' | '"')) => escaped.push_str("\\\\"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn needs_space(before: &str, after: &str) -> bool {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    match (before.chars().next_back(), after.chars().next()) {
+        (Some(a), Some(b)) => (is_word(a) || a == '"') && (is_word(b) || b == '"'),
+        _ => false,
+    }
+}
@@ -0,0 +1,78 @@
+use emoji_lang::dialect::Dialect;
+use emoji_lang::token::TokenType;
+use logos::Logos;
+
+/// Tokens of the text (Lox-flavored) input language that aren't keywords or operators
+#[derive(Logos, Debug, PartialEq, Clone, Copy)]
+enum TextToken {
+    #[regex(r"[ \t\r\n\f]+")]
+    Whitespace,
+    #[regex(r"//[^\n\r]*")]
+    Comment,
+    // Only a comment in Python; elsewhere `#` is just a character the language doesn't know
+    #[regex(r"#[^\n\r]*")]
+    HashComment,
+    #[regex(r#""([^"\\\n\r]|\\.)*"|'([^'\\\n\r]|\\.)*'"#)]
+    Text,
+    #[regex(r"[0-9]*\.?[0-9]+")]
+    Number,
+    #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*")]
+    Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LexemeKind {
+    Whitespace,
+    Comment,
+    Text,
+    Number,
+    Word,
+    // A keyword or operator spelled by the dialect
+    Spelled(TokenType),
+    // Characters the input language doesn't know about
+    Unknown,
+}
+
+/// A token along with the exact text it was read from
+#[derive(Debug, Clone)]
+pub struct Lexeme<'a> {
+    pub kind: LexemeKind,
+    pub text: &'a str,
+    pub offset: usize,
+}
+
+/// Splits `input` into lexemes that, concatenated, give back `input` exactly.
+/// `hash_comments` reads `#` to the end of the line as a comment, like Python does.
+pub fn tokenize<'a>(input: &'a str, dialect: &Dialect, hash_comments: bool) -> Vec<Lexeme<'a>> {
+    let mut lexemes = Vec::new();
+    let mut offset = 0;
+    while offset < input.len() {
+        let rest = &input[offset..];
+        let mut lexer = TextToken::lexer(rest);
+        let token = lexer.next().and_then(Result::ok);
+        let len = lexer.span().end;
+        let (kind, len) = match token {
+            Some(TextToken::Whitespace) => (LexemeKind::Whitespace, len),
+            Some(TextToken::Comment) => (LexemeKind::Comment, len),
+            Some(TextToken::HashComment) if hash_comments => (LexemeKind::Comment, len),
+            Some(TextToken::Text) => (LexemeKind::Text, len),
+            // Dialect spellings take the longest match, so `<=` is never read as `<` then `=`,
+            // and only whole words count, so `format` is never read as `for`
+            _ => match dialect.match_text(rest) {
+                Some((token_type, len)) => (LexemeKind::Spelled(token_type), len),
+                None => match token {
+                    Some(TextToken::Number) => (LexemeKind::Number, len),
+                    Some(TextToken::Word) => (LexemeKind::Word, len),
+                    _ => (LexemeKind::Unknown, rest.chars().next().map_or(1, char::len_utf8)),
+                },
+            },
+        };
+        lexemes.push(Lexeme {
+            kind,
+            text: &rest[..len],
+            offset,
+        });
+        offset += len;
+    }
+    lexemes
+}